@@ -0,0 +1,177 @@
+//! Barnes-Hut approximation of the gravity sum.
+//!
+//! A quadtree is built over the bodies every evaluation. Each node stores the
+//! total mass and center of mass of everything beneath it, and nodes that are
+//! small compared to their distance from the body being evaluated (`s / d <
+//! theta`) are treated as a single point mass. This brings the cost from
+//! O(n²) down to roughly O(n log n).
+
+use nalgebra::Vector2;
+
+use crate::sim::gravity_force;
+
+/// Bodies closer together than the tree can resolve at this depth are lumped
+/// into a single node instead of subdividing forever.
+const MAX_DEPTH: u32 = 32;
+
+#[derive(Clone, Copy)]
+enum NodeKind {
+    Empty,
+    /// Holds exactly one body.
+    Leaf(usize),
+    /// Index of the first of four consecutive children.
+    Internal(usize),
+    /// Several coincident bodies that could not be separated.
+    Aggregate,
+}
+
+struct Node {
+    center: Vector2<f32>,
+    half_size: f32,
+    mass: f32,
+    com: Vector2<f32>,
+    kind: NodeKind,
+}
+
+impl Node {
+    fn new(center: Vector2<f32>, half_size: f32) -> Self {
+        Self {
+            center,
+            half_size,
+            mass: 0.0,
+            com: center,
+            kind: NodeKind::Empty,
+        }
+    }
+
+    fn contains(&self, pos: Vector2<f32>) -> bool {
+        (pos - self.center).abs().max() <= self.half_size
+    }
+
+    fn quadrant(&self, pos: Vector2<f32>) -> usize {
+        (pos.x >= self.center.x) as usize | ((pos.y >= self.center.y) as usize) << 1
+    }
+}
+
+pub struct QuadTree {
+    nodes: Vec<Node>,
+}
+
+impl QuadTree {
+    /// Builds a tree over `positions`, weighting each by the matching entry of
+    /// `masses`. Bodies with non-finite positions are left out.
+    pub fn build(positions: &[Vector2<f32>], masses: &[f32]) -> Self {
+        let mut min = Vector2::repeat(f32::INFINITY);
+        let mut max = Vector2::repeat(f32::NEG_INFINITY);
+        for pos in positions.iter().filter(|p| is_finite(p)) {
+            min = min.inf(pos);
+            max = max.sup(pos);
+        }
+        let (center, half_size) = if min.x <= max.x {
+            let extent = (max - min).max().max(1.0);
+            ((min + max) * 0.5, extent * 0.5 * 1.01)
+        } else {
+            (Vector2::zeros(), 1.0)
+        };
+
+        let mut tree = Self {
+            nodes: vec![Node::new(center, half_size)],
+        };
+        for (i, pos) in positions.iter().enumerate() {
+            if is_finite(pos) {
+                tree.insert(0, i, positions, masses, 0);
+            }
+        }
+        tree
+    }
+
+    fn insert(
+        &mut self,
+        node: usize,
+        body: usize,
+        positions: &[Vector2<f32>],
+        masses: &[f32],
+        depth: u32,
+    ) {
+        let (pos, mass) = (positions[body], masses[body]);
+        let n = &mut self.nodes[node];
+        let total = n.mass + mass;
+        if total > 0.0 {
+            n.com = (n.com * n.mass + pos * mass) / total;
+        }
+        n.mass = total;
+
+        match n.kind {
+            NodeKind::Empty => n.kind = NodeKind::Leaf(body),
+            NodeKind::Aggregate => {}
+            NodeKind::Leaf(_) if depth >= MAX_DEPTH => n.kind = NodeKind::Aggregate,
+            NodeKind::Leaf(existing) => {
+                // The existing body is already part of this node's aggregate,
+                // so it only needs pushing down into the new children.
+                let first = self.subdivide(node);
+                for b in [existing, body] {
+                    let q = self.nodes[node].quadrant(positions[b]);
+                    self.insert(first + q, b, positions, masses, depth + 1);
+                }
+            }
+            NodeKind::Internal(first) => {
+                let q = n.quadrant(pos);
+                self.insert(first + q, body, positions, masses, depth + 1);
+            }
+        }
+    }
+
+    /// Splits `node` into four empty children and returns the first index.
+    fn subdivide(&mut self, node: usize) -> usize {
+        let first = self.nodes.len();
+        let center = self.nodes[node].center;
+        let quarter = self.nodes[node].half_size * 0.5;
+        for q in 0..4 {
+            let dx = if q & 1 == 1 { quarter } else { -quarter };
+            let dy = if q & 2 == 2 { quarter } else { -quarter };
+            self.nodes
+                .push(Node::new(center + Vector2::new(dx, dy), quarter));
+        }
+        self.nodes[node].kind = NodeKind::Internal(first);
+        first
+    }
+
+    /// Gravitational acceleration felt by body `index` at `pos`. Nodes whose
+    /// size over distance falls below `theta` are approximated as point masses,
+    /// unless they contain `pos`: at large `theta` such a node could pass the
+    /// test and the body would be pulled by its own mass.
+    pub fn acceleration(
+        &self,
+        index: usize,
+        pos: Vector2<f32>,
+        g: f32,
+        theta: f32,
+        softening: f32,
+    ) -> Vector2<f32> {
+        let mut acc = Vector2::zeros();
+        let mut stack = vec![0];
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i];
+            match node.kind {
+                NodeKind::Empty => {}
+                NodeKind::Leaf(body) if body == index => {}
+                NodeKind::Leaf(_) | NodeKind::Aggregate => {
+                    acc += gravity_force(g, pos, node.com, 1.0, node.mass, softening);
+                }
+                NodeKind::Internal(first) => {
+                    let dist = (node.com - pos).norm();
+                    if 2.0 * node.half_size < theta * dist && !node.contains(pos) {
+                        acc += gravity_force(g, pos, node.com, 1.0, node.mass, softening);
+                    } else {
+                        stack.extend(first..first + 4);
+                    }
+                }
+            }
+        }
+        acc
+    }
+}
+
+fn is_finite(v: &Vector2<f32>) -> bool {
+    v.x.is_finite() && v.y.is_finite()
+}
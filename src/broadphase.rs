@@ -0,0 +1,58 @@
+//! Spatial-hash broadphase for collision detection.
+//!
+//! Bodies are bucketed into a uniform grid whose cells are as wide as the
+//! largest body, so two bodies can only touch if they sit in the same or
+//! adjacent cells. Only those pairs are handed to the exact overlap test,
+//! bringing collision detection from O(n²) down to roughly O(n) for scenes
+//! of similarly sized bodies.
+
+use std::collections::HashMap;
+
+use nalgebra::Vector2;
+
+/// Neighboring cells checked from each cell: itself plus half of the eight
+/// around it, so each pair of cells is visited exactly once.
+const NEIGHBORS: [(i32, i32); 5] = [(0, 0), (1, 0), (1, 1), (0, 1), (-1, 1)];
+
+/// Index pairs `(i, j)` with `i < j` of bodies that may overlap, in
+/// ascending order so callers resolve them in the same order as a full
+/// pairwise scan.
+pub fn candidate_pairs(positions: &[Vector2<f32>], radii: &[f32]) -> Vec<(usize, usize)> {
+    let max_radius = radii.iter().copied().fold(0.0, f32::max);
+    if max_radius <= 0.0 || !max_radius.is_finite() {
+        return Vec::new();
+    }
+    let cell_size = 2.0 * max_radius;
+    let mut cells: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+    for (i, pos) in positions.iter().enumerate() {
+        if !(pos.x.is_finite() && pos.y.is_finite()) {
+            continue;
+        }
+        let cell = (
+            (pos.x / cell_size).floor() as i32,
+            (pos.y / cell_size).floor() as i32,
+        );
+        cells.entry(cell).or_default().push(i);
+    }
+
+    let mut pairs = Vec::new();
+    for (&(x, y), bodies) in &cells {
+        for (dx, dy) in NEIGHBORS {
+            if (dx, dy) == (0, 0) {
+                for (k, &i) in bodies.iter().enumerate() {
+                    for &j in &bodies[k + 1..] {
+                        pairs.push((i.min(j), i.max(j)));
+                    }
+                }
+            } else if let Some(others) = cells.get(&(x.wrapping_add(dx), y.wrapping_add(dy))) {
+                for &i in bodies {
+                    for &j in others {
+                        pairs.push((i.min(j), i.max(j)));
+                    }
+                }
+            }
+        }
+    }
+    pairs.sort_unstable();
+    pairs
+}
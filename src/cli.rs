@@ -0,0 +1,92 @@
+//! Command-line arguments.
+
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: egui_gravisim [OPTIONS] [SCENE]
+
+  SCENE            Scene file to load on startup
+  --window WxH     Initial window size in pixels (default 1280x720)
+  --bodies N       Start with N random bodies
+  --g G            Gravitational constant, overriding the scene's
+  --headless N     Simulate N random bodies without opening a window
+  --steps M        Physics steps to run in headless mode (default 1000)";
+
+/// Physics steps run by `--headless` when `--steps` isn't given.
+const DEFAULT_STEPS: usize = 1000;
+const DEFAULT_WINDOW_SIZE: [f32; 2] = [1280.0, 720.0];
+
+#[derive(Debug)]
+pub struct Args {
+    /// Inner size of the window, in logical pixels.
+    pub window_size: [f32; 2],
+    /// Random bodies added on startup, after any scene.
+    pub bodies: usize,
+    pub g: Option<f32>,
+    pub scene: Option<PathBuf>,
+    /// Run a benchmark instead of opening a window.
+    pub headless: bool,
+    /// Physics steps in a headless run.
+    pub steps: usize,
+}
+
+impl Args {
+    /// Parses the arguments after the program name.
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args {
+            window_size: DEFAULT_WINDOW_SIZE,
+            bodies: 0,
+            g: None,
+            scene: None,
+            headless: false,
+            steps: DEFAULT_STEPS,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--window" => parsed.window_size = window_size(&arg, args.next())?,
+                "--bodies" => parsed.bodies = value(&arg, args.next())?,
+                "--g" => {
+                    let g: f32 = value(&arg, args.next())?;
+                    if !(g.is_finite() && g > 0.0) {
+                        return Err(format!("`{arg}` must be positive"));
+                    }
+                    parsed.g = Some(g);
+                }
+                "--headless" => {
+                    parsed.headless = true;
+                    parsed.bodies = value(&arg, args.next())?;
+                }
+                "--steps" => parsed.steps = value(&arg, args.next())?,
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                }
+                _ if arg.starts_with('-') => return Err(format!("unknown argument `{arg}`")),
+                _ if parsed.scene.is_none() => parsed.scene = Some(arg.into()),
+                _ => return Err(format!("unexpected argument `{arg}`")),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Parses the value following `flag`.
+fn value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("`{flag}` needs a value"))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value `{value}` for `{flag}`"))
+}
+
+/// Parses a `WxH` window size following `flag`.
+fn window_size(flag: &str, value: Option<String>) -> Result<[f32; 2], String> {
+    let value = value.ok_or_else(|| format!("`{flag}` needs a value"))?;
+    let invalid = || format!("invalid window size `{value}`, expected e.g. 1280x720");
+    let (width, height) = value.split_once('x').ok_or_else(invalid)?;
+    let width: f32 = width.parse().map_err(|_| invalid())?;
+    let height: f32 = height.parse().map_err(|_| invalid())?;
+    if !(width >= 1.0 && height >= 1.0) {
+        return Err(invalid());
+    }
+    Ok([width, height])
+}
@@ -0,0 +1,71 @@
+//! Purely visual effects that don't feed back into the simulation.
+
+use std::f32::consts::TAU;
+
+use eframe::egui::Color32;
+use nalgebra::Vector2;
+use rand::Rng;
+
+use crate::sim::MergeRecord;
+
+/// How long a merge particle lives, in seconds.
+pub const PARTICLE_LIFETIME: f32 = 0.5;
+/// Particles thrown out by each merge.
+const PARTICLES_PER_MERGE: usize = 24;
+/// Most particles alive at once; bursts beyond this are dropped.
+const MAX_PARTICLES: usize = 5000;
+/// Fastest particle speed relative to the merged body's radius, per second.
+const BURST_SPEED: f32 = 4.0;
+
+/// A non-gravitating spark, in world space.
+#[derive(Clone, Copy, Debug)]
+pub struct Particle {
+    pub pos: Vector2<f32>,
+    pub vel: Vector2<f32>,
+    pub color: Color32,
+    /// Seconds since the particle was spawned.
+    pub age: f32,
+}
+
+impl Particle {
+    /// Remaining brightness in `[0, 1]`, fading linearly over its lifetime.
+    pub fn fade(&self) -> f32 {
+        (1.0 - self.age / PARTICLE_LIFETIME).clamp(0.0, 1.0)
+    }
+}
+
+/// Adds a burst of particles flying out from where the two bodies of
+/// `merge` met, moving along with the merged body.
+pub fn burst(particles: &mut Vec<Particle>, merge: &MergeRecord, rng: &mut impl Rng) {
+    let [a, b] = &merge.parts;
+    let mass = a.mass + b.mass;
+    let (pos, vel) = if mass > 0.0 {
+        (
+            (a.pos * a.mass + b.pos * b.mass) / mass,
+            (a.vel * a.mass + b.vel * b.mass) / mass,
+        )
+    } else {
+        ((a.pos + b.pos) * 0.5, (a.vel + b.vel) * 0.5)
+    };
+    let max_speed = a.radius.max(b.radius) * BURST_SPEED;
+    let room = MAX_PARTICLES.saturating_sub(particles.len());
+    particles.extend((0..PARTICLES_PER_MERGE.min(room)).map(|i| {
+        let angle = rng.random::<f32>() * TAU;
+        let speed = max_speed * rng.random_range(0.3..1.0);
+        Particle {
+            pos,
+            vel: vel + Vector2::new(angle.cos(), angle.sin()) * speed,
+            color: if i % 2 == 0 { a.color } else { b.color },
+            age: 0.0,
+        }
+    }));
+}
+
+/// Moves and ages all particles by `dt` seconds, dropping expired ones.
+pub fn update(particles: &mut Vec<Particle>, dt: f32) {
+    particles.retain_mut(|p| {
+        p.pos += p.vel * dt;
+        p.age += dt;
+        p.age < PARTICLE_LIFETIME
+    });
+}
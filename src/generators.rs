@@ -0,0 +1,194 @@
+//! Procedural generators that add many bodies to an existing scene.
+
+use std::f32::consts::TAU;
+
+use eframe::egui::Color32;
+use nalgebra::Vector2;
+use rand::Rng;
+
+use crate::sim::{Body, circular_velocity};
+
+/// Random velocity kick given to each asteroid, as a fraction of its
+/// circular orbital speed.
+const BELT_VELOCITY_JITTER: f32 = 0.02;
+const ASTEROID_COLOR: Color32 = Color32::from_rgb(160, 150, 140);
+
+/// Parameters of the asteroid-belt generator.
+#[derive(Clone, Debug)]
+pub struct BeltParams {
+    pub count: usize,
+    /// Inner and outer edge of the belt, from the central body, in world
+    /// units.
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    /// Mean asteroid mass.
+    pub mass: f32,
+    /// Relative spread of asteroid masses around the mean, in `[0, 1)`.
+    pub mass_spread: f32,
+}
+
+impl Default for BeltParams {
+    fn default() -> Self {
+        Self {
+            count: 300,
+            inner_radius: 250.0,
+            outer_radius: 320.0,
+            mass: 1.0,
+            mass_spread: 0.5,
+        }
+    }
+}
+
+/// A ring of small bodies on roughly circular orbits around `center`,
+/// spread evenly over the ring's area.
+pub fn asteroid_belt(center: &Body, g: f32, params: &BeltParams, rng: &mut impl Rng) -> Vec<Body> {
+    let inner_sq = params.inner_radius.min(params.outer_radius).powi(2);
+    let outer_sq = params.outer_radius.max(params.inner_radius).powi(2);
+    (0..params.count)
+        .map(|_| {
+            let angle = rng.random::<f32>() * TAU;
+            let distance = (inner_sq + (outer_sq - inner_sq) * rng.random::<f32>()).sqrt();
+            let offset = Vector2::new(angle.cos(), angle.sin()) * distance;
+            let orbital = circular_velocity(g, center.mass, offset);
+            let kick_angle = rng.random::<f32>() * TAU;
+            let kick = Vector2::new(kick_angle.cos(), kick_angle.sin())
+                * (orbital.norm() * BELT_VELOCITY_JITTER * rng.random::<f32>());
+            let mass = params.mass * (1.0 + params.mass_spread * rng.random_range(-1.0..1.0));
+            // Unit density, so the radius follows from the mass.
+            Body::new(
+                center.pos + offset,
+                center.vel + orbital + kick,
+                1.0,
+                mass.max(f32::EPSILON).sqrt(),
+                ASTEROID_COLOR,
+            )
+        })
+        .collect()
+}
+
+/// How far each spiral arm winds, in radians from the inner edge of the disk
+/// to the outer edge.
+const GALAXY_ARM_TWIST: f32 = 1.5 * TAU;
+/// Angular scatter of stars around their arm, in radians.
+const GALAXY_ARM_SPREAD: f32 = 0.6;
+/// Inner edge of the disk as a fraction of its radius, keeping stars out of
+/// the central body.
+const GALAXY_INNER_FRACTION: f32 = 0.08;
+const GALAXY_CORE_RADIUS: f32 = 20.0;
+const GALAXY_STAR_MASS: f32 = 1.0;
+const GALAXY_STAR_RADIUS: f32 = 1.5;
+
+/// Parameters of the spiral-galaxy generator.
+#[derive(Clone, Debug)]
+pub struct GalaxyParams {
+    pub count: usize,
+    /// Outer radius of the disk, in world units.
+    pub radius: f32,
+    pub central_mass: f32,
+    pub arms: usize,
+}
+
+impl Default for GalaxyParams {
+    fn default() -> Self {
+        Self {
+            count: 1000,
+            radius: 600.0,
+            central_mass: 1e7,
+            arms: 2,
+        }
+    }
+}
+
+/// A central mass at `center` surrounded by a disk of stars concentrated
+/// along trailing spiral arms. Each star gets the circular speed for the
+/// central mass plus the disk mass inside its radius, so the disk starts in
+/// near-equilibrium and shears the arms out as it relaxes.
+pub fn spiral_galaxy(
+    center: Vector2<f32>,
+    g: f32,
+    params: &GalaxyParams,
+    rng: &mut impl Rng,
+) -> Vec<Body> {
+    let core = Body::new(
+        center,
+        Vector2::zeros(),
+        params.central_mass / (GALAXY_CORE_RADIUS * GALAXY_CORE_RADIUS),
+        GALAXY_CORE_RADIUS,
+        Color32::from_rgb(255, 230, 180),
+    );
+    let arms = params.arms.max(1);
+    let outer = params.radius;
+    let inner = outer * GALAXY_INNER_FRACTION;
+    let disk_mass = params.count as f32 * GALAXY_STAR_MASS;
+    let inner_color = Color32::from_rgb(255, 220, 170);
+    let outer_color = Color32::from_rgb(150, 180, 255);
+
+    let stars = (0..params.count).map(|i| {
+        // Uniform over the annulus's area, so the enclosed disk mass grows
+        // with r² and is easy to estimate below.
+        let enclosed = rng.random::<f32>();
+        let r = (inner * inner + (outer * outer - inner * inner) * enclosed).sqrt();
+        let t = (r - inner) / (outer - inner);
+        let arm = (i % arms) as f32 * TAU / arms as f32;
+        let angle = arm - GALAXY_ARM_TWIST * t + GALAXY_ARM_SPREAD * rng.random_range(-0.5..0.5);
+        let offset = Vector2::new(angle.cos(), angle.sin()) * r;
+        let vel = circular_velocity(g, params.central_mass + disk_mass * enclosed, offset);
+        Body::new(
+            center + offset,
+            vel,
+            GALAXY_STAR_MASS / (GALAXY_STAR_RADIUS * GALAXY_STAR_RADIUS),
+            GALAXY_STAR_RADIUS,
+            inner_color.lerp_to_gamma(outer_color, t),
+        )
+    });
+    std::iter::once(core).chain(stars).collect()
+}
+
+const CLUSTER_STAR_RADIUS: f32 = 2.0;
+
+/// Parameters of the Gaussian-cluster generator.
+#[derive(Clone, Debug)]
+pub struct ClusterParams {
+    pub count: usize,
+    /// Standard deviation of the positions around the center, in world
+    /// units.
+    pub position_sigma: f32,
+    /// Standard deviation of each velocity component, in world units per
+    /// second.
+    pub velocity_sigma: f32,
+}
+
+impl Default for ClusterParams {
+    fn default() -> Self {
+        Self {
+            count: 200,
+            position_sigma: 100.0,
+            velocity_sigma: 2.0,
+        }
+    }
+}
+
+/// A cloud of unit-density bodies with normally distributed positions
+/// around `center` and normally distributed velocities. Started nearly at
+/// rest, the cloud collapses under its own gravity.
+pub fn gaussian_cluster(
+    center: Vector2<f32>,
+    params: &ClusterParams,
+    rng: &mut impl Rng,
+) -> Vec<Body> {
+    (0..params.count)
+        .map(|_| {
+            let pos = center + standard_normal(rng) * params.position_sigma;
+            let vel = standard_normal(rng) * params.velocity_sigma;
+            Body::new(pos, vel, 1.0, CLUSTER_STAR_RADIUS, Body::DEFAULT_COLOR)
+        })
+        .collect()
+}
+
+/// Two independent standard normal samples, via the Box-Muller transform.
+fn standard_normal(rng: &mut impl Rng) -> Vector2<f32> {
+    // `1 - u` lies in (0, 1], keeping the logarithm finite.
+    let radius = (-2.0 * (1.0 - rng.random::<f32>()).ln()).sqrt();
+    let angle = rng.random::<f32>() * TAU;
+    Vector2::new(angle.cos(), angle.sin()) * radius
+}
@@ -0,0 +1,48 @@
+//! Windowless benchmark runs, for timing the solver and checking physics
+//! changes against known output.
+
+use std::time::Instant;
+
+use crate::{GravisimApp, PHYSICS_DT};
+
+/// Runs `steps` physics steps on `app`'s scene and prints the timing and
+/// final conserved quantities.
+pub fn run(mut app: GravisimApp, steps: usize) {
+    let bodies = app.sim.bodies.len();
+    let initial = app.sim.energy();
+    let initial_angular = app.sim.angular_momentum();
+
+    let start = Instant::now();
+    for _ in 0..steps {
+        app.step_physics(PHYSICS_DT);
+    }
+    let elapsed = start.elapsed();
+
+    let energy = app.sim.energy();
+    let momentum = app.sim.momentum();
+    let per_step = elapsed.as_secs_f64() / steps.max(1) as f64;
+    println!(
+        "{bodies} bodies, {steps} steps ({:.1} s simulated)",
+        steps as f32 * PHYSICS_DT
+    );
+    println!(
+        "elapsed: {:.3} s ({:.3} ms/step)",
+        elapsed.as_secs_f64(),
+        per_step * 1e3
+    );
+    println!("bodies left: {}", app.sim.bodies.len());
+    println!(
+        "energy: {:.6e} (initial {:.6e}, drift {:+.3e})",
+        energy.total(),
+        initial.total(),
+        energy.total() - initial.total()
+    );
+    println!("momentum: ({:.6e}, {:.6e})", momentum.x, momentum.y);
+    let angular = app.sim.angular_momentum();
+    println!(
+        "angular momentum: {:.6e} (initial {:.6e}, drift {:+.3e})",
+        angular,
+        initial_angular,
+        angular - initial_angular
+    );
+}
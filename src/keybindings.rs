@@ -0,0 +1,195 @@
+//! Remappable keyboard shortcuts, saved as JSON.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use eframe::egui::{InputState, Key};
+use serde::{Deserialize, Serialize};
+
+/// Something a keyboard shortcut can trigger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Command {
+    Reset,
+    ToggleHud,
+    Screenshot,
+    ToggleCollisionMode,
+    FollowSelected,
+    LockToCenterOfMass,
+    ToggleOrbitMode,
+    ToggleMeasureMode,
+    SpawnCluster,
+    Pause,
+    Step,
+    Slower,
+    Faster,
+    RealTime,
+    FitView,
+    Deselect,
+    DeleteSelected,
+    /// Triggered with Ctrl (Cmd on macOS) held.
+    Undo,
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    NudgeUp,
+    NudgeDown,
+    NudgeLeft,
+    NudgeRight,
+}
+
+impl Command {
+    pub const ALL: [Command; 26] = [
+        Command::Reset,
+        Command::ToggleHud,
+        Command::Screenshot,
+        Command::ToggleCollisionMode,
+        Command::FollowSelected,
+        Command::LockToCenterOfMass,
+        Command::ToggleOrbitMode,
+        Command::ToggleMeasureMode,
+        Command::SpawnCluster,
+        Command::Pause,
+        Command::Step,
+        Command::Slower,
+        Command::Faster,
+        Command::RealTime,
+        Command::FitView,
+        Command::Deselect,
+        Command::DeleteSelected,
+        Command::Undo,
+        Command::PanUp,
+        Command::PanDown,
+        Command::PanLeft,
+        Command::PanRight,
+        Command::NudgeUp,
+        Command::NudgeDown,
+        Command::NudgeLeft,
+        Command::NudgeRight,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Command::Reset => "Reset",
+            Command::ToggleHud => "Toggle HUD",
+            Command::Screenshot => "Screenshot",
+            Command::ToggleCollisionMode => "Toggle Bounce/Merge",
+            Command::FollowSelected => "Follow Selected",
+            Command::LockToCenterOfMass => "Lock View to Center of Mass",
+            Command::ToggleOrbitMode => "Toggle Orbit Mode",
+            Command::ToggleMeasureMode => "Toggle Measure Mode",
+            Command::SpawnCluster => "Spawn Cluster at Cursor",
+            Command::Pause => "Pause",
+            Command::Step => "Step (paused)",
+            Command::Slower => "Slower",
+            Command::Faster => "Faster",
+            Command::RealTime => "Real Time (1×)",
+            Command::FitView => "Fit View",
+            Command::Deselect => "Deselect",
+            Command::DeleteSelected => "Delete Selected",
+            Command::Undo => "Undo Spawn/Merge (with Ctrl)",
+            Command::PanUp => "Pan Up",
+            Command::PanDown => "Pan Down",
+            Command::PanLeft => "Pan Left",
+            Command::PanRight => "Pan Right",
+            Command::NudgeUp => "Nudge Selected Up (paused, Shift: Velocity)",
+            Command::NudgeDown => "Nudge Selected Down",
+            Command::NudgeLeft => "Nudge Selected Left",
+            Command::NudgeRight => "Nudge Selected Right",
+        }
+    }
+
+    fn default_key(self) -> Key {
+        match self {
+            Command::Reset => Key::R,
+            Command::ToggleHud => Key::H,
+            Command::Screenshot => Key::P,
+            Command::ToggleCollisionMode => Key::E,
+            Command::FollowSelected => Key::F,
+            Command::LockToCenterOfMass => Key::L,
+            Command::ToggleOrbitMode => Key::O,
+            Command::ToggleMeasureMode => Key::M,
+            Command::SpawnCluster => Key::C,
+            Command::Pause => Key::Space,
+            Command::Step => Key::Period,
+            Command::Slower => Key::OpenBracket,
+            Command::Faster => Key::CloseBracket,
+            Command::RealTime => Key::Backslash,
+            Command::FitView => Key::Home,
+            Command::Deselect => Key::Escape,
+            Command::DeleteSelected => Key::Delete,
+            Command::Undo => Key::Z,
+            Command::PanUp => Key::W,
+            Command::PanDown => Key::S,
+            Command::PanLeft => Key::A,
+            Command::PanRight => Key::D,
+            Command::NudgeUp => Key::ArrowUp,
+            Command::NudgeDown => Key::ArrowDown,
+            Command::NudgeLeft => Key::ArrowLeft,
+            Command::NudgeRight => Key::ArrowRight,
+        }
+    }
+}
+
+/// The key bound to each [`Command`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyBindings {
+    keys: BTreeMap<Command, Key>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            keys: Command::ALL
+                .into_iter()
+                .map(|command| (command, command.default_key()))
+                .collect(),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn key(&self, command: Command) -> Key {
+        self.keys
+            .get(&command)
+            .copied()
+            .unwrap_or_else(|| command.default_key())
+    }
+
+    /// Binds `command` to `key`. A command already on `key` takes over
+    /// `command`'s old key, so no two commands ever share one.
+    pub fn set(&mut self, command: Command, key: Key) {
+        let old = self.key(command);
+        if let Some(other) = Command::ALL.into_iter().find(|&c| self.key(c) == key) {
+            self.keys.insert(other, old);
+        }
+        self.keys.insert(command, key);
+    }
+
+    /// Whether `command`'s key was pressed (or auto-repeated) this frame.
+    pub fn pressed(&self, input: &InputState, command: Command) -> bool {
+        input.key_pressed(self.key(command))
+    }
+
+    /// Whether `command`'s key is held down.
+    pub fn down(&self, input: &InputState, command: Command) -> bool {
+        input.key_down(self.key(command))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)
+    }
+
+    /// Reads bindings written by [`KeyBindings::save`]. Commands missing from
+    /// the file keep their default keys.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        let saved: KeyBindings = serde_json::from_str(&json)?;
+        let mut bindings = KeyBindings::default();
+        bindings.keys.extend(saved.keys);
+        Ok(bindings)
+    }
+}
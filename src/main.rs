@@ -1,217 +1,3176 @@
+use std::collections::{HashSet, VecDeque};
+use std::path::Path;
+
 use eframe::{App, Frame, egui};
 use egui::{Color32, Pos2, Vec2};
 use nalgebra::Vector2;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+mod barnes_hut;
+mod broadphase;
+mod cli;
+mod effects;
+mod generators;
+mod headless;
+mod keybindings;
+mod presets;
+mod recording;
+mod render;
+mod scene;
+mod sim;
+#[cfg(feature = "simd")]
+mod simd;
 
-const G: f32 = 0.0005;
+use effects::Particle;
+use generators::{BeltParams, ClusterParams, GalaxyParams};
+use keybindings::{Command, KeyBindings};
+use presets::{Preset, PresetParams};
+use recording::{CsvExport, Recording, Replay};
+use render::{BodyColors, ColorMode, PotentialMap, Starfield};
+use scene::{Clipboard, Scene};
+use sim::{
+    Body, Boundary, CollisionMode, CollisionStats, Energy, GravitySolver, Integrator, MergeRecord,
+    OrbitKind, Simulation,
+};
 
-#[derive(Clone)]
-struct Body {
-    pos: Vector2<f32>,
-    vel: Vector2<f32>,
-    mass: f32,
-    radius: f32,
-    color: Color32,
+/// Fixed physics timestep, independent of the display refresh rate.
+const PHYSICS_DT: f32 = 1.0 / 120.0;
+/// Longest frame time fed to the accumulator, so a long hitch doesn't make
+/// the simulation try to catch up with hundreds of steps at once.
+const MAX_FRAME_DT: f32 = 0.25;
+/// Range of the adaptive timestep's bounds, in seconds.
+const ADAPTIVE_DT_RANGE: std::ops::RangeInclusive<f32> = 1e-5..=0.05;
+/// Adaptive steps aim to fit this many steps into the closest encounter time.
+const STEPS_PER_ENCOUNTER: f32 = 50.0;
+/// Most an adaptive step may grow over the previous one, so the step eases
+/// back up after a close pass instead of jumping straight to the maximum.
+const MAX_DT_GROWTH: f32 = 1.1;
+/// Most physics steps run in one frame. Time beyond this is dropped, so tiny
+/// adaptive steps slow the simulation down rather than freezing the UI.
+const MAX_STEPS_PER_FRAME: usize = 2000;
+/// Half-width of the center-of-mass crosshair, in screen pixels.
+const CROSSHAIR_SIZE: f32 = 8.0;
+/// Above this many bodies the O(n²) energy readout is only refreshed every
+/// `ENERGY_REFRESH_INTERVAL` seconds instead of every frame.
+const ENERGY_EVERY_FRAME_LIMIT: usize = 500;
+const ENERGY_REFRESH_INTERVAL: f32 = 0.5;
+/// Fraction of the viewport filled by the scene after fitting the view.
+const FIT_VIEW_FILL: f32 = 0.9;
+/// Smallest world-space extent fitted to the view, so a lone small body
+/// isn't blown up to fill the screen.
+const MIN_FIT_EXTENT: f32 = 200.0;
+/// Allowed zoom range, in pixels per world unit.
+const ZOOM_RANGE: std::ops::RangeInclusive<f32> = 1e-4..=1e4;
+/// Natural-log change in zoom per point of scrolling. The zoom factor is
+/// exponential in the scroll distance, so the same scroll zooms by the same
+/// amount whether it arrives in one frame or spread over many.
+const ZOOM_SENSITIVITY: f32 = 0.005;
+/// Sizes the new-body slider and scroll-while-aiming allow.
+const SIZE_RANGE: std::ops::RangeInclusive<f32> = 1.0..=500.0;
+/// Largest zoom factor applied in a single frame; bounds a burst of scroll
+/// events after a stall.
+const MAX_ZOOM_STEP: f32 = 4.0;
+/// Minimum on-screen radius used when clicking on bodies, in pixels.
+const PICK_RADIUS: f32 = 4.0;
+/// Gap between a selected body and its highlight ring, in pixels.
+const SELECTION_RING_GAP: f32 = 4.0;
+/// Range of the simulation speed multiplier.
+const TIME_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.1..=10.0;
+/// Factor applied to the time scale per `[` or `]` press.
+const TIME_SCALE_STEP: f32 = 1.5;
+/// How long the time scale stays on screen after a hotkey change, in seconds.
+const TIME_SCALE_NOTICE: f64 = 1.5;
+/// Segments in the dashed Roche limit circle.
+const ROCHE_SEGMENTS: usize = 96;
+/// How far an arrow key moves the selected body, in screen pixels.
+const NUDGE_PIXELS: f32 = 1.0;
+/// How much Shift+arrow changes the selected body's velocity, in screen
+/// pixels per second.
+const NUDGE_SPEED_PIXELS: f32 = 5.0;
+/// Pulses per second of the selected body's highlight ring.
+const SELECTION_PULSE_RATE: f64 = 1.5;
+/// Redraw interval for the selection pulse while nothing else moves.
+const SELECTION_PULSE_FRAME: std::time::Duration = std::time::Duration::from_millis(33);
+/// Most steps forward-simulated for the spawn trajectory preview.
+const PREDICTION_STEPS: usize = 300;
+/// Step size for the trajectory preview, coarser than the real step so the
+/// preview reaches further ahead.
+const PREDICTION_DT: f32 = 4.0 * PHYSICS_DT;
+/// Rough cap on pairwise gravity evaluations per frame for the preview; big
+/// scenes get a shorter prediction instead of a frozen UI.
+const PREDICTION_BUDGET: usize = 4_000_000;
+/// File the key bindings are saved to and loaded from, in the working
+/// directory.
+const KEYBINDINGS_PATH: &str = "keybindings.json";
+/// Seed used for random generation until the user picks another.
+const DEFAULT_SEED: u64 = 1;
+/// Seed for the particle effects' generator, kept apart from `rng` so eye
+/// candy doesn't change what the seed generates.
+const EFFECTS_SEED: u64 = 0xB0057;
+/// Seconds of motion a comet tail stretches back over, past the threshold
+/// speed.
+const COMET_TAIL_TIME: f32 = 0.5;
+/// Longest comet tail drawn, in screen pixels.
+const MAX_COMET_TAIL: f32 = 120.0;
+/// Translucent disks stacked to draw a glow halo.
+const GLOW_RINGS: usize = 6;
+/// Combined opacity of a glow halo's center at the threshold mass.
+const GLOW_ALPHA: f32 = 0.25;
+/// Decades of mass above the threshold after which halos stop growing.
+const GLOW_MAX_BOOST: f32 = 3.0;
+/// Side of a merge particle, in screen pixels.
+const PARTICLE_SIZE: f32 = 2.0;
+/// Number of recent frame times averaged for the smoothed FPS readout.
+const FRAME_TIME_HISTORY: usize = 120;
+/// Simulated seconds over which the collision rate is averaged.
+const COLLISION_RATE_WINDOW: f64 = 1.0;
+/// Energy samples kept for the energy plot.
+const ENERGY_PLOT_SAMPLES: usize = 1000;
+/// Number of undoable actions kept.
+const UNDO_LIMIT: usize = 100;
+/// Bodies whose on-screen radius is below this are drawn as single points.
+const POINT_LOD_RADIUS: f32 = 1.0;
+/// Side length of a point-rendered body, in pixels.
+const POINT_SIZE: f32 = 1.5;
+/// Closest the world grid lines get on screen, in pixels.
+const MIN_GRID_SPACING: f32 = 40.0;
+/// Longest the scale bar gets, in screen pixels.
+const SCALE_BAR_MAX_LEN: f32 = 100.0;
+/// Distance of the scale bar from the corner of the view, in pixels.
+const SCALE_BAR_MARGIN: f32 = 16.0;
+/// Fixed seed for the background stars, so they look the same every run.
+const STARFIELD_SEED: u64 = 0x57A2;
+/// Screen pixels the starfield scrolls per world unit the camera moves.
+const STARFIELD_PARALLAX: f32 = 0.05;
+/// Side length of the minimap, in pixels.
+const MINIMAP_SIZE: f32 = 160.0;
+/// Smallest cell of the potential heatmap, in screen pixels.
+const POTENTIAL_CELL: f32 = 12.0;
+/// Rough cap on body-sample evaluations per potential refresh; bigger scenes
+/// get coarser cells.
+const POTENTIAL_BUDGET: usize = 2_000_000;
+/// Seconds between potential heatmap refreshes.
+const POTENTIAL_REFRESH_INTERVAL: f32 = 0.1;
+/// Distance between force-field arrows, in screen pixels.
+const FIELD_ARROW_SPACING: f32 = 48.0;
+/// Rough cap on body-sample evaluations for the force field per frame.
+const FIELD_BUDGET: usize = 1_000_000;
+/// Most body labels drawn per frame, so a dense scene doesn't drown in text.
+const MAX_LABELS: usize = 300;
+/// Rough cap on pairwise evaluations per frame for the tidal axes.
+const TIDAL_BUDGET: usize = 1_000_000;
+/// Longest vector arrow drawn, in screen pixels.
+const MAX_ARROW_LEN: f32 = 150.0;
+/// Radius of the random-body disk per square root of the body count.
+const RANDOM_SPREAD: f32 = 40.0;
+/// Radii drawn for random bodies.
+const RANDOM_SIZE_RANGE: std::ops::Range<f32> = 2.0..8.0;
+/// Fastest initial speed of a random body, in world units per second.
+const RANDOM_MAX_SPEED: f32 = 20.0;
+
+/// What happens to a dragged body's velocity when it is dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DropVelocity {
+    Keep,
+    Zero,
+    /// Launch it like a new body, from the drag vector.
+    Fling,
 }
 
-impl Body {
-    fn new(pos: Vector2<f32>, vel: Vector2<f32>, density: f32, size: f32) -> Self {
-        let radius = size;
-        let mass = density * radius * radius;
-        Self {
-            pos,
-            vel,
-            mass,
-            radius,
-            color: Color32::from_rgb(200, 200, 255),
+impl DropVelocity {
+    const ALL: [DropVelocity; 3] = [DropVelocity::Keep, DropVelocity::Zero, DropVelocity::Fling];
+
+    fn label(self) -> &'static str {
+        match self {
+            DropVelocity::Keep => "Keep velocity",
+            DropVelocity::Zero => "Zero velocity",
+            DropVelocity::Fling => "Fling",
         }
     }
+}
 
-    fn apply_gravity(&mut self, other: &Body) {
-        let dir = other.pos - self.pos;
-        let dist_sq = dir.norm_squared();
-        if dist_sq < 1.0 {
-            return;
+/// Where symmetric spawning puts the mirror image of each new body.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Symmetry {
+    Off,
+    /// Point reflection through the center of mass.
+    Point,
+    /// Reflection across the vertical line through the center of mass.
+    VerticalAxis,
+    /// Reflection across the horizontal line through the center of mass.
+    HorizontalAxis,
+}
+
+impl Symmetry {
+    const ALL: [Symmetry; 4] = [
+        Symmetry::Off,
+        Symmetry::Point,
+        Symmetry::VerticalAxis,
+        Symmetry::HorizontalAxis,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Symmetry::Off => "Off",
+            Symmetry::Point => "Through center of mass",
+            Symmetry::VerticalAxis => "Across vertical axis",
+            Symmetry::HorizontalAxis => "Across horizontal axis",
+        }
+    }
+
+    /// Mirrors `v` about `center`, or `None` when symmetry is off. Positions
+    /// mirror about the center of mass and velocities about its velocity.
+    fn mirror(self, v: Vector2<f32>, center: Vector2<f32>) -> Option<Vector2<f32>> {
+        let d = v - center;
+        let d = match self {
+            Symmetry::Off => return None,
+            Symmetry::Point => -d,
+            Symmetry::VerticalAxis => Vector2::new(-d.x, d.y),
+            Symmetry::HorizontalAxis => Vector2::new(d.x, -d.y),
+        };
+        Some(center + d)
+    }
+}
+
+/// Text drawn next to each body.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BodyLabel {
+    None,
+    Id,
+    Mass,
+    IdAndMass,
+}
+
+impl BodyLabel {
+    const ALL: [BodyLabel; 4] = [
+        BodyLabel::None,
+        BodyLabel::Id,
+        BodyLabel::Mass,
+        BodyLabel::IdAndMass,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            BodyLabel::None => "None",
+            BodyLabel::Id => "ID",
+            BodyLabel::Mass => "Mass",
+            BodyLabel::IdAndMass => "ID and mass",
         }
-        let force_mag = G * self.mass * other.mass / dist_sq;
-        let force = dir.normalize() * force_mag;
-        self.vel += force / self.mass;
     }
 
-    fn update(&mut self, dt: f32) {
-        self.pos += self.vel * dt;
+    /// The label text for `body`, or `None` if labels are off.
+    fn text(self, body: &Body) -> Option<String> {
+        match self {
+            BodyLabel::None => None,
+            BodyLabel::Id => Some(format!("#{}", body.id)),
+            BodyLabel::Mass => Some(format!("{:.3}", body.mass)),
+            BodyLabel::IdAndMass => Some(format!("#{} {:.3}", body.id, body.mass)),
+        }
     }
 }
 
+/// Something Ctrl+Z can take back.
+enum Action {
+    /// A body spawned by the user, by ID.
+    Spawn(u64),
+    /// Bodies added together by a generator, undone as one.
+    SpawnGroup(Vec<u64>),
+    /// The merges of one physics step, undone as one so that a burst of
+    /// collisions doesn't push everything else off the stack.
+    Merge(Vec<MergeRecord>),
+}
+
+/// One end of a ruler measurement.
+#[derive(Clone, Copy)]
+enum Anchor {
+    Point(Vector2<f32>),
+    /// Tracks the body as it moves.
+    Body(u64),
+}
+
+/// An existing body being repositioned with the mouse.
+struct BodyDrag {
+    id: u64,
+    /// Offset from the pointer to the body center, so the body doesn't jump.
+    grab_offset: Vector2<f32>,
+    /// Where the drag started, for fling velocities.
+    start: Vector2<f32>,
+}
+
 struct GravisimApp {
-    bodies: Vec<Body>,
+    sim: Simulation,
+    /// View center, relative to `frame_origin()`.
     camera_pos: Vector2<f32>,
+    /// Draw everything relative to the center of mass, so a drifting system
+    /// stays put on screen. Only affects rendering.
+    barycentric_view: bool,
+    /// In the barycentric view, also draw velocities relative to the center
+    /// of mass.
+    barycentric_velocities: bool,
     zoom: f32,
+    /// Screen rect of the simulation view from the last frame.
+    viewport: egui::Rect,
     selected_size: f32,
     selected_density: f32,
+    selected_color: Color32,
+    /// Give each new body a random hue instead of `selected_color`.
+    random_color: bool,
+    /// Seed for `rng`; the same seed and inputs reproduce the same scene.
+    seed: u64,
+    /// Source of all randomness, so runs are reproducible.
+    rng: StdRng,
     selected_pos: Option<Vector2<f32>>,
+    /// ID of the selected body.
+    selected_body: Option<u64>,
+    /// ID of a second body picked with shift-click, for pair readouts.
+    second_selected: Option<u64>,
+    /// IDs of the bodies picked by the last box selection.
+    multi_selection: Vec<u64>,
+    /// World point where a Ctrl-drag box selection started.
+    box_select: Option<Vector2<f32>>,
+    /// Keep the camera centered on the selected body.
+    follow_selected: bool,
+    /// Keep the camera centered on the center of mass. Mutually exclusive
+    /// with `follow_selected`.
+    lock_to_com: bool,
+    dragging_body: Option<BodyDrag>,
+    /// Most recent last, capped at `UNDO_LIMIT`.
+    undo_stack: VecDeque<Action>,
+    drop_velocity: DropVelocity,
+    /// Spawn bodies on circular orbits instead of aiming them by dragging.
+    orbit_mode: bool,
+    /// Launch speed per pixel of drag, in world units per second.
+    launch_strength: f32,
+    /// Body that orbit mode puts new bodies around, instead of whichever
+    /// pulls hardest at the spawn point.
+    orbit_center: Option<u64>,
+    /// Body whose context menu is open.
+    context_body: Option<u64>,
+    /// Spawn a mirror image along with each new body.
+    symmetry: Symmetry,
+    /// Clicks place ruler ends instead of selecting or spawning.
+    measure_mode: bool,
+    /// Up to two ruler ends, in the order they were placed.
+    ruler: Vec<Anchor>,
+    /// In the edit panel, keep density fixed so editing mass or radius
+    /// updates the other.
+    edit_lock_density: bool,
     show_hud: bool,
-    elastic: bool,
+    preset_params: PresetParams,
+    belt_params: BeltParams,
+    galaxy_params: GalaxyParams,
+    cluster_params: ClusterParams,
+    /// Result of the last save/load, shown in the HUD.
+    status_message: Option<String>,
+    bindings: KeyBindings,
+    /// Command waiting for a key press to be rebound to.
+    rebinding: Option<Command>,
+    /// CSV imports add to the current bodies instead of replacing them.
+    csv_import_append: bool,
+    color_mode: ColorMode,
+    show_grid: bool,
+    show_potential: bool,
+    show_field: bool,
+    /// Last sampled potential heatmap and how long ago it was sampled.
+    potential: Option<PotentialMap>,
+    potential_age: f32,
+    show_starfield: bool,
+    starfield: Starfield,
+    show_scale_bar: bool,
+    show_minimap: bool,
+    /// Throw out a burst of sparks when bodies merge.
+    show_merge_bursts: bool,
+    particles: Vec<Particle>,
+    effects_rng: StdRng,
+    show_trails: bool,
+    /// Draw a soft glow around bodies heavier than `glow_mass`.
+    show_glow: bool,
+    glow_mass: f32,
+    /// Draw a short motion streak behind bodies faster than `comet_speed`.
+    show_comet_tails: bool,
+    comet_speed: f32,
+    /// Draw all body disks as one mesh instead of one circle shape each.
+    mesh_bodies: bool,
+    show_center_of_mass: bool,
+    /// Number of past positions kept per body when trails are shown.
+    trail_length: usize,
+    show_velocity: bool,
+    body_label: BodyLabel,
+    /// Outline the Roche limit of the selected body around its primary.
+    show_roche_limit: bool,
+    /// World-space arrow length per unit of speed.
+    velocity_arrow_scale: f32,
+    show_acceleration: bool,
+    /// World-space arrow length per unit of acceleration.
+    acceleration_arrow_scale: f32,
+    /// Draw each body's tidal stretching axis.
+    show_tidal: bool,
+    /// Screen length of a tidal axis per unit of tidal acceleration across
+    /// the body, before zooming.
+    tidal_scale: f32,
+    paused: bool,
+    /// Number of single steps taken since the simulation was last paused.
+    paused_steps: u32,
+    /// Simulated seconds per real second.
+    time_scale: f32,
+    /// When a hotkey last changed `time_scale`, in egui's input time.
+    time_scale_changed_at: Option<f64>,
+    /// Last computed energy and angular momentum readouts and how long ago
+    /// they were computed.
+    energy: Energy,
+    angular_momentum: f64,
+    energy_age: f32,
+    /// Simulated seconds since the scene was loaded or reset.
+    sim_time: f64,
+    show_energy_plot: bool,
+    /// Collisions per simulated second over the last full rate window.
+    collision_rate: f64,
+    /// `sim_time` and collision total when the current rate window began.
+    rate_window_start: (f64, u64),
+    /// Recent `(sim_time, energy)` samples, oldest first.
+    energy_history: VecDeque<(f64, Energy)>,
+    /// Recent raw frame times in seconds, newest last.
+    frame_times: VecDeque<f32>,
+    /// Frame time not yet consumed by a physics step.
+    physics_accumulator: f32,
+    /// Shrink the step below `max_dt` (down to `min_dt`) while bodies are in
+    /// close, fast encounters, instead of always stepping by `PHYSICS_DT`.
+    /// Replays still play one recorded step per `PHYSICS_DT`.
+    adaptive_dt: bool,
+    min_dt: f32,
+    max_dt: f32,
+    /// Size of the most recent physics step.
+    step_dt: f32,
+    /// Equal sub-steps each physics step is split into, each with its own
+    /// gravity, integration and collision pass.
+    substeps: u32,
+    /// Captured steps, kept after recording stops so they can be replayed.
+    recording: Recording,
+    is_recording: bool,
+    /// Also stream trajectories to `csv_path` while recording.
+    export_csv: bool,
+    csv_path: String,
+    csv_export: Option<CsvExport>,
+    /// Set while the recording is played back instead of simulating.
+    replay: Option<Replay>,
+    /// Capture the view on the next frame, with the HUD hidden.
+    screenshot_requested: bool,
 }
 
 impl Default for GravisimApp {
     fn default() -> Self {
         Self {
-            bodies: Vec::new(),
+            sim: Simulation::default(),
             camera_pos: Vector2::new(0.0, 0.0),
+            barycentric_view: false,
+            barycentric_velocities: true,
             zoom: 1.0,
+            viewport: egui::Rect::from_min_size(Pos2::ZERO, Vec2::new(1280.0, 720.0)),
             selected_size: 50.0,
             selected_density: 1.0,
+            selected_color: Body::DEFAULT_COLOR,
+            random_color: false,
+            seed: DEFAULT_SEED,
+            rng: StdRng::seed_from_u64(DEFAULT_SEED),
             selected_pos: None,
+            selected_body: None,
+            second_selected: None,
+            multi_selection: Vec::new(),
+            box_select: None,
+            follow_selected: false,
+            lock_to_com: false,
+            dragging_body: None,
+            undo_stack: VecDeque::new(),
+            drop_velocity: DropVelocity::Keep,
+            orbit_mode: false,
+            launch_strength: 0.05,
+            symmetry: Symmetry::Off,
+            orbit_center: None,
+            context_body: None,
+            measure_mode: false,
+            ruler: Vec::new(),
+            edit_lock_density: true,
             show_hud: true,
-            elastic: false,
+            preset_params: PresetParams::default(),
+            belt_params: BeltParams::default(),
+            galaxy_params: GalaxyParams::default(),
+            cluster_params: ClusterParams::default(),
+            status_message: None,
+            csv_import_append: false,
+            bindings: KeyBindings::default(),
+            rebinding: None,
+            color_mode: ColorMode::default(),
+            show_grid: false,
+            show_potential: false,
+            show_field: false,
+            potential: None,
+            potential_age: f32::INFINITY,
+            show_starfield: true,
+            starfield: Starfield::new(STARFIELD_SEED),
+            show_scale_bar: true,
+            show_minimap: true,
+            show_merge_bursts: true,
+            particles: Vec::new(),
+            effects_rng: StdRng::seed_from_u64(EFFECTS_SEED),
+            show_trails: false,
+            show_comet_tails: false,
+            show_glow: false,
+            glow_mass: 1000.0,
+            comet_speed: 50.0,
+            mesh_bodies: true,
+            show_center_of_mass: false,
+            trail_length: 200,
+            show_velocity: false,
+            body_label: BodyLabel::None,
+            show_roche_limit: false,
+            velocity_arrow_scale: 5.0,
+            show_acceleration: false,
+            acceleration_arrow_scale: 500.0,
+            show_tidal: false,
+            tidal_scale: 500.0,
+            paused: false,
+            paused_steps: 0,
+            time_scale: 1.0,
+            time_scale_changed_at: None,
+            energy: Energy::default(),
+            angular_momentum: 0.0,
+            energy_age: f32::INFINITY,
+            sim_time: 0.0,
+            show_energy_plot: false,
+            collision_rate: 0.0,
+            rate_window_start: (0.0, 0),
+            energy_history: VecDeque::with_capacity(ENERGY_PLOT_SAMPLES),
+            frame_times: VecDeque::with_capacity(FRAME_TIME_HISTORY),
+            physics_accumulator: 0.0,
+            adaptive_dt: false,
+            min_dt: PHYSICS_DT / 100.0,
+            max_dt: PHYSICS_DT,
+            step_dt: PHYSICS_DT,
+            substeps: 1,
+            recording: Recording::default(),
+            is_recording: false,
+            export_csv: false,
+            csv_path: "trajectories.csv".to_owned(),
+            csv_export: None,
+            replay: None,
+            screenshot_requested: false,
         }
     }
 }
 
-fn nalgebra_from_vec2(v: Vec2) -> Vector2<f32> {
-    Vector2::new(v.x, v.y)
-}
+impl GravisimApp {
+    fn save_scene(&self, path: &Path) -> std::io::Result<()> {
+        let scene = Scene {
+            bodies: self.sim.bodies.clone(),
+            camera_pos: self.view_center(),
+            zoom: self.zoom,
+            g: self.sim.g,
+            softening: self.sim.softening,
+        };
+        scene.save(path)
+    }
 
-impl App for GravisimApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
-        let input = ctx.input(|i| i.clone());
-        let dt = input.stable_dt;
+    /// Opens a save dialog and writes the scene to the chosen file.
+    fn save_scene_dialog(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Scene", &["json"])
+            .set_file_name("scene.json")
+            .save_file()
+        else {
+            return;
+        };
+        self.status_message = Some(match self.save_scene(&path) {
+            Ok(()) => format!("Saved {}", path.display()),
+            Err(err) => format!("Save failed: {err}"),
+        });
+    }
 
-        // Handle input
-        if input.key_pressed(egui::Key::R) {
-            self.bodies.clear();
+    /// Replaces the current scene with one read from `path`, pausing so the
+    /// loaded state can be inspected before it starts moving.
+    fn load_scene(&mut self, path: &Path) -> std::io::Result<()> {
+        let scene = Scene::load(path)?;
+        self.replay = None;
+        self.paused = true;
+        self.paused_steps = 0;
+        self.physics_accumulator = 0.0;
+        self.selected_pos = None;
+        self.selected_body = None;
+        self.undo_stack.clear();
+        self.restart_clock();
+        self.sim.bodies = scene.bodies;
+        self.sim.g = scene.g;
+        self.sim.softening = scene.softening;
+        self.sim.compute_accelerations();
+        self.camera_pos = scene.camera_pos - self.frame_origin();
+        self.zoom = scene.zoom.clamp(*ZOOM_RANGE.start(), *ZOOM_RANGE.end());
+        Ok(())
+    }
+
+    /// Opens a file dialog and loads the chosen scene.
+    fn load_scene_dialog(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Scene", &["json"])
+            .pick_file()
+        else {
+            return;
+        };
+        self.status_message = Some(match self.load_scene(&path) {
+            Ok(()) => format!("Loaded {}", path.display()),
+            Err(err) => format!("Load failed: {err}"),
+        });
+    }
+
+    /// Adds the bodies listed in a CSV file, or replaces the current ones
+    /// with them (pausing, as when loading a scene). Returns how many were
+    /// read; a file with none can't replace the scene.
+    fn import_csv(&mut self, path: &Path) -> std::io::Result<usize> {
+        let bodies = scene::import_csv(path)?;
+        let count = bodies.len();
+        if self.csv_import_append {
+            // Append to the live scene, not to the replay frame on screen
+            self.stop_replay();
+            self.add_generated(bodies);
+        } else if bodies.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "no bodies in file",
+            ));
+        } else {
+            self.replay = None;
+            self.paused = true;
+            self.paused_steps = 0;
+            self.physics_accumulator = 0.0;
+            self.selected_pos = None;
+            self.selected_body = None;
+            self.undo_stack.clear();
+            self.restart_clock();
+            self.sim.bodies = bodies;
+            self.sim.compute_accelerations();
+            self.fit_view();
+        }
+        Ok(count)
+    }
+
+    /// Opens a file dialog and imports bodies from the chosen CSV file.
+    fn import_csv_dialog(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .pick_file()
+        else {
+            return;
+        };
+        self.status_message = Some(match self.import_csv(&path) {
+            Ok(count) => format!("Imported {count} bodies from {}", path.display()),
+            Err(err) => format!("Import failed: {err}"),
+        });
+    }
+
+    /// Crops a captured frame to `view`, the simulation view as it was when
+    /// the capture was requested, and writes it to a timestamped PNG in the
+    /// working directory.
+    fn save_screenshot(
+        &mut self,
+        image: &egui::ColorImage,
+        view: egui::Rect,
+        pixels_per_point: f32,
+    ) {
+        let bounds = egui::Rect::from_min_size(
+            Pos2::ZERO,
+            Vec2::new(image.width() as f32, image.height() as f32) / pixels_per_point,
+        );
+        let view = image.region(&view.intersect(bounds), Some(pixels_per_point));
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = format!("gravisim-{timestamp}.png");
+        self.status_message = Some(
+            match image::save_buffer(
+                &path,
+                view.as_raw(),
+                view.width() as u32,
+                view.height() as u32,
+                image::ColorType::Rgba8,
+            ) {
+                Ok(()) => format!("Saved {path}"),
+                Err(err) => format!("Screenshot failed: {err}"),
+            },
+        );
+    }
+
+    /// Replaces the scene with `preset` and fits the view to it.
+    fn load_preset(&mut self, preset: Preset) {
+        self.replay = None;
+        self.sim.bodies = preset.bodies(self.sim.g, &self.preset_params);
+        self.selected_body = None;
+        self.undo_stack.clear();
+        self.restart_clock();
+        self.sim.compute_accelerations();
+        self.physics_accumulator = 0.0;
+        self.selected_pos = None;
+        self.fit_view();
+    }
+
+    /// Centers the camera on the bounding box of all bodies (including their
+    /// radii) and zooms so it fits the viewport with a small margin. An empty
+    /// scene resets the view.
+    fn fit_view(&mut self) {
+        let Some((min, max)) = self.scene_bounds() else {
+            self.camera_pos = Vector2::zeros();
+            self.zoom = 1.0;
+            return;
+        };
+        self.camera_pos = (min + max) * 0.5 - self.frame_origin();
+        let extent = (max - min).sup(&Vector2::repeat(MIN_FIT_EXTENT));
+        let size = self.viewport.size();
+        let zoom = (size.x / extent.x).min(size.y / extent.y) * FIT_VIEW_FILL;
+        if zoom.is_finite() && zoom > 0.0 {
+            self.zoom = zoom.clamp(*ZOOM_RANGE.start(), *ZOOM_RANGE.end());
+        }
+    }
+
+    /// Bounding box of all bodies including their radii, or `None` for an
+    /// empty scene.
+    fn scene_bounds(&self) -> Option<(Vector2<f32>, Vector2<f32>)> {
+        let mut min = Vector2::repeat(f32::INFINITY);
+        let mut max = Vector2::repeat(f32::NEG_INFINITY);
+        for body in &self.sim.bodies {
+            min = min.inf(&body.pos.add_scalar(-body.radius));
+            max = max.sup(&body.pos.add_scalar(body.radius));
+        }
+        (min.x <= max.x && min.y <= max.y).then_some((min, max))
+    }
+
+    /// Runs as many physics steps as fit in the elapsed frame time, carrying
+    /// the remainder over to the next frame. The time scale changes how many
+    /// steps run, never the size of a step, so fast-forward stays as stable
+    /// as real time.
+    fn advance_physics(&mut self, frame_dt: f32) {
+        self.physics_accumulator += frame_dt.min(MAX_FRAME_DT) * self.time_scale;
+        for _ in 0..MAX_STEPS_PER_FRAME {
+            let dt = self.next_step_dt();
+            if self.physics_accumulator < dt {
+                return;
+            }
+            self.step_physics(dt);
+            self.physics_accumulator -= dt;
+        }
+        self.physics_accumulator = 0.0;
+    }
+
+    /// Size of the next physics step: `PHYSICS_DT`, or with the adaptive
+    /// timestep a fraction of the closest encounter time, growing by at most
+    /// `MAX_DT_GROWTH` per step and kept within `min_dt..=max_dt`.
+    fn next_step_dt(&self) -> f32 {
+        if !self.adaptive_dt {
+            return PHYSICS_DT;
+        }
+        let target = self
+            .sim
+            .closest_encounter_time()
+            .map_or(self.max_dt, |t| t / STEPS_PER_ENCOUNTER);
+        let max = self.max_dt;
+        target
+            .min(self.step_dt * MAX_DT_GROWTH)
+            .clamp(self.min_dt.min(max), max)
+    }
+
+    /// Advances the simulation by one step of `dt` seconds, in `substeps`
+    /// equal parts, independent of the UI.
+    fn step_physics(&mut self, dt: f32) {
+        let substep = dt / self.substeps as f32;
+        for _ in 0..self.substeps {
+            self.sim.step(substep);
+        }
+        self.step_dt = dt;
+        self.sim_time += f64::from(dt);
+        let (start_time, start_count) = self.rate_window_start;
+        let window = self.sim_time - start_time;
+        if window >= COLLISION_RATE_WINDOW {
+            let count = self.sim.collisions.total();
+            self.collision_rate = (count - start_count) as f64 / window;
+            self.rate_window_start = (self.sim_time, count);
+        }
+        self.collect_merges();
+        if self.is_recording {
+            self.capture_step();
+        }
+    }
+
+    /// Records the current state and appends it to the CSV export, stopping
+    /// the recording if either fails.
+    fn capture_step(&mut self) {
+        if !self.recording.capture(&self.sim.bodies) {
+            self.stop_recording();
+            self.status_message = Some("Recording stopped: memory limit reached".to_owned());
+        } else if let Some(export) = &mut self.csv_export
+            && let Err(err) = export.write_step(&self.sim.bodies)
+        {
+            self.csv_export = None;
+            self.stop_recording();
+            self.status_message = Some(format!("CSV export failed: {err}"));
+        }
+    }
+
+    /// Discards any previous recording and captures from the current state.
+    fn start_recording(&mut self) {
+        if self.export_csv {
+            match CsvExport::create(Path::new(&self.csv_path)) {
+                Ok(export) => self.csv_export = Some(export),
+                Err(err) => {
+                    self.status_message = Some(format!("CSV export failed: {err}"));
+                    return;
+                }
+            }
+        }
+        self.recording.clear();
+        self.is_recording = true;
+        self.capture_step();
+    }
+
+    /// Stops capturing and closes the CSV export, if any.
+    fn stop_recording(&mut self) {
+        self.is_recording = false;
+        if let Some(export) = self.csv_export.take() {
+            self.status_message = Some(match export.finish() {
+                Ok(()) => format!("Exported {}", self.csv_path),
+                Err(err) => format!("CSV export failed: {err}"),
+            });
+        }
+    }
+
+    /// Swaps the live bodies out for the first recorded frame.
+    fn start_replay(&mut self) {
+        if self.recording.is_empty() {
+            return;
+        }
+        self.stop_recording();
+        self.selected_pos = None;
+        self.dragging_body = None;
+        self.replay = Some(Replay {
+            frame: 0,
+            playing: true,
+            clock: 0.0,
+            live_bodies: std::mem::take(&mut self.sim.bodies),
+        });
+        self.sim.bodies = self.recording.bodies(0);
+    }
+
+    /// Ends playback and brings back the bodies from before it started.
+    fn stop_replay(&mut self) {
+        if let Some(replay) = self.replay.take() {
+            self.sim.bodies = replay.live_bodies;
+        }
+    }
+
+    /// Moves playback forward at one recorded step per `PHYSICS_DT` of
+    /// (time-scaled) frame time and shows the current frame.
+    fn advance_replay(&mut self, frame_dt: f32) {
+        let Some(replay) = &mut self.replay else {
+            return;
+        };
+        let last = self.recording.len() - 1;
+        if replay.playing {
+            replay.clock += frame_dt.min(MAX_FRAME_DT) * self.time_scale;
+            let steps = (replay.clock / PHYSICS_DT) as usize;
+            replay.clock -= steps as f32 * PHYSICS_DT;
+            replay.frame = (replay.frame + steps).min(last);
+            replay.playing = replay.frame < last;
+        }
+        // Rebuilt every frame so edits can't leak into the playback.
+        self.sim.bodies = self.recording.bodies(replay.frame);
+    }
+
+    /// Bottom bar with playback controls and the scrub slider.
+    fn replay_bar(&mut self, ctx: &egui::Context) {
+        let last = self.recording.len().saturating_sub(1);
+        let Some(replay) = &mut self.replay else {
+            return;
+        };
+        let mut stop = false;
+        egui::TopBottomPanel::bottom("replay_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let label = if replay.playing { "Pause" } else { "Play" };
+                if ui.button(label).clicked() {
+                    if !replay.playing && replay.frame == last {
+                        replay.frame = 0;
+                    }
+                    replay.playing = !replay.playing;
+                }
+                stop = ui.button("Exit replay").clicked();
+                ui.label(format!("t = {:.2} s", replay.frame as f32 * PHYSICS_DT));
+                ui.spacing_mut().slider_width = (ui.available_width() - 60.0).max(100.0);
+                if ui
+                    .add(egui::Slider::new(&mut replay.frame, 0..=last))
+                    .dragged()
+                {
+                    replay.playing = false;
+                }
+            });
+        });
+        if stop {
+            self.stop_replay();
+        }
+    }
+
+    /// Scatters `count` bodies drawn from `rng` over a disk around the
+    /// origin, sized so the average spacing stays about the same.
+    fn spawn_random_bodies(&mut self, count: usize) {
+        let spread = RANDOM_SPREAD * (count as f32).sqrt();
+        for _ in 0..count {
+            let angle = self.rng.random::<f32>() * std::f32::consts::TAU;
+            let distance = spread * self.rng.random::<f32>().sqrt();
+            let pos = Vector2::new(angle.cos(), angle.sin()) * distance;
+            let vel_angle = self.rng.random::<f32>() * std::f32::consts::TAU;
+            let vel = Vector2::new(vel_angle.cos(), vel_angle.sin())
+                * self.rng.random_range(0.0..RANDOM_MAX_SPEED);
+            let size = self.rng.random_range(RANDOM_SIZE_RANGE);
+            let color = self.next_body_color();
+            self.sim
+                .bodies
+                .push(Body::new(pos, vel, self.selected_density, size, color));
+        }
+        self.sim.compute_accelerations();
+    }
+
+    fn record_frame_time(&mut self, dt: f32) {
+        if self.frame_times.len() == FRAME_TIME_HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(dt);
+    }
+
+    /// Recomputes the energy and angular momentum readouts, throttled for
+    /// large scenes.
+    fn refresh_energy(&mut self, dt: f32) {
+        self.energy_age += dt;
+        if self.sim.bodies.len() <= ENERGY_EVERY_FRAME_LIMIT
+            || self.energy_age >= ENERGY_REFRESH_INTERVAL
+        {
+            self.energy = self.sim.energy();
+            self.angular_momentum = self.sim.angular_momentum();
+            self.energy_age = 0.0;
+            let is_new = self
+                .energy_history
+                .back()
+                .is_none_or(|&(t, _)| t < self.sim_time);
+            if self.show_energy_plot && is_new {
+                if self.energy_history.len() == ENERGY_PLOT_SAMPLES {
+                    self.energy_history.pop_front();
+                }
+                self.energy_history.push_back((self.sim_time, self.energy));
+            }
+        }
+    }
+
+    /// Starts simulated time and the collision statistics over for a new
+    /// scene.
+    fn restart_clock(&mut self) {
+        self.sim_time = 0.0;
+        self.energy_history.clear();
+        self.sim.collisions = CollisionStats::default();
+        self.sim.culled = 0;
+        self.collision_rate = 0.0;
+        self.rate_window_start = (0.0, 0);
+    }
+
+    /// Bottom panel plotting recent energy samples against simulated time.
+    fn energy_plot(&self, ctx: &egui::Context) {
+        if !self.show_energy_plot {
+            return;
+        }
+        let series = |value: fn(&Energy) -> f64| -> egui_plot::PlotPoints {
+            self.energy_history
+                .iter()
+                .map(|(t, energy)| [*t, value(energy)])
+                .collect()
+        };
+        egui::TopBottomPanel::bottom("energy_plot")
+            .resizable(true)
+            .default_height(160.0)
+            .show(ctx, |ui| {
+                egui_plot::Plot::new("energy")
+                    .legend(egui_plot::Legend::default())
+                    .x_axis_label("Time (s)")
+                    .show(ui, |plot| {
+                        plot.line(egui_plot::Line::new(series(|e| e.kinetic)).name("Kinetic"));
+                        plot.line(egui_plot::Line::new(series(|e| e.potential)).name("Potential"));
+                        plot.line(egui_plot::Line::new(series(Energy::total)).name("Total"));
+                    });
+            });
+    }
+
+    /// Current position of a ruler end, or `None` if its body is gone.
+    fn anchor_pos(&self, anchor: Anchor) -> Option<Vector2<f32>> {
+        match anchor {
+            Anchor::Point(pos) => Some(pos),
+            Anchor::Body(id) => self.sim.find(id).map(|i| self.sim.bodies[i].pos),
+        }
+    }
+
+    /// HUD readout for a finished ruler: the distance, plus the relative
+    /// speed and mutual gravity when both ends are bodies.
+    fn ruler_readout(&self, ui: &mut egui::Ui) {
+        let [a, b] = self.ruler[..] else {
+            ui.label("Click two points or bodies to measure");
+            return;
+        };
+        let (Some(pos_a), Some(pos_b)) = (self.anchor_pos(a), self.anchor_pos(b)) else {
+            return;
+        };
+        match (a, b) {
+            (Anchor::Body(a), Anchor::Body(b)) => self.pair_readout(ui, a, b),
+            _ => {
+                ui.label(format!("Distance: {:.2}", (pos_b - pos_a).norm()));
+            }
+        }
+    }
+
+    /// Separation, relative velocity and mutual gravity of bodies `a` and
+    /// `b`, if both still exist.
+    fn pair_readout(&self, ui: &mut egui::Ui, a: u64, b: u64) {
+        let (Some(a), Some(b)) = (self.sim.find(a), self.sim.find(b)) else {
+            return;
+        };
+        let (a, b) = (&self.sim.bodies[a], &self.sim.bodies[b]);
+        let relative_vel = b.vel - a.vel;
+        let force =
+            sim::gravity_force(self.sim.g, a.pos, b.pos, a.mass, b.mass, self.sim.softening);
+        ui.label(format!("Distance: {:.2}", (b.pos - a.pos).norm()));
+        ui.label(format!(
+            "Relative velocity: ({:.2}, {:.2}) |v| = {:.2}",
+            relative_vel.x,
+            relative_vel.y,
+            relative_vel.norm()
+        ));
+        ui.label(format!("Gravitational force: {:.3}", force.norm()));
+    }
+
+    /// World position shown at `screen` in the last frame's view.
+    fn screen_to_world(&self, screen: Pos2) -> Vector2<f32> {
+        nalgebra_from_vec2(screen - self.viewport.center()) / self.zoom + self.view_center()
+    }
+
+    /// World position the view is measured from: the center of mass in the
+    /// barycentric view, otherwise the origin.
+    fn frame_origin(&self) -> Vector2<f32> {
+        if self.barycentric_view {
+            self.sim.center_of_mass().unwrap_or_else(Vector2::zeros)
+        } else {
+            Vector2::zeros()
+        }
+    }
+
+    /// World position at the center of the view.
+    fn view_center(&self) -> Vector2<f32> {
+        self.camera_pos + self.frame_origin()
+    }
+
+    /// Switches the barycentric view on or off without moving the view, and
+    /// drops trails recorded in the old frame.
+    fn set_barycentric_view(&mut self, enabled: bool) {
+        let center = self.view_center();
+        self.barycentric_view = enabled;
+        self.camera_pos = center - self.frame_origin();
+        for body in &mut self.sim.bodies {
+            body.trail.clear();
+        }
+    }
+
+    /// Adds generated bodies to the scene as a single undoable action.
+    fn add_generated(&mut self, bodies: Vec<Body>) {
+        self.push_undo(Action::SpawnGroup(bodies.iter().map(|b| b.id).collect()));
+        self.sim.bodies.extend(bodies);
+        self.sim.compute_accelerations();
+    }
+
+    /// Surrounds the most massive body with an asteroid belt.
+    fn add_asteroid_belt(&mut self) {
+        let Some(center) = self
+            .sim
+            .bodies
+            .iter()
+            .max_by(|a, b| a.mass.total_cmp(&b.mass))
+        else {
+            self.status_message = Some("An asteroid belt needs a body to orbit".to_owned());
+            return;
+        };
+        let belt = generators::asteroid_belt(center, self.sim.g, &self.belt_params, &mut self.rng);
+        self.add_generated(belt);
+    }
+
+    /// Launch velocity for a body spawned by a drag from `start` to `end`.
+    /// In orbit mode the drag is ignored in favor of a circular orbit.
+    fn spawn_velocity(&self, start: Vector2<f32>, end: Vector2<f32>) -> Vector2<f32> {
+        let mass = self.selected_density * self.selected_size * self.selected_size;
+        let center = self.orbit_center.and_then(|id| self.sim.find(id));
+        self.orbit_mode
+            .then(|| match center {
+                Some(center) => Some(self.sim.orbit_velocity_around(center, start, mass)),
+                None => self.sim.orbit_velocity(start, mass),
+            })
+            .flatten()
+            .unwrap_or_else(|| self.drag_velocity(start, end))
+    }
+
+    /// Launch velocity for a mouse drag between the world points `start` and
+    /// `end`. The speed follows the drag's on-screen length in pixels, so the
+    /// same gesture launches equally fast at any zoom.
+    fn drag_velocity(&self, start: Vector2<f32>, end: Vector2<f32>) -> Vector2<f32> {
+        (end - start) * self.zoom * self.launch_strength
+    }
+
+    /// Color for the next spawned body.
+    fn next_body_color(&mut self) -> Color32 {
+        if self.random_color {
+            let hue = self.rng.random::<f32>();
+            egui::ecolor::Hsva::new(hue, 0.6, 1.0, 1.0).into()
+        } else {
+            self.selected_color
+        }
+    }
+
+    fn push_undo(&mut self, action: Action) {
+        if self.undo_stack.len() == UNDO_LIMIT {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(action);
+    }
+
+    /// Moves merges reported by the simulation onto the undo stack, as a
+    /// single action. The parts' trails are dropped: they would only be
+    /// restored as a stale line, and can hold thousands of points each.
+    fn collect_merges(&mut self) {
+        let mut merges = std::mem::take(&mut self.sim.merges);
+        if merges.is_empty() {
+            return;
+        }
+        for merge in &mut merges {
+            if self.show_merge_bursts {
+                effects::burst(&mut self.particles, merge, &mut self.effects_rng);
+            }
+            for part in &mut merge.parts {
+                part.trail = VecDeque::new();
+            }
+        }
+        self.push_undo(Action::Merge(merges));
+    }
+
+    /// Reverts the most recent action that still applies. Actions whose
+    /// bodies have since disappeared (deleted, or absorbed in a later merge)
+    /// are skipped.
+    fn undo(&mut self) {
+        while let Some(action) = self.undo_stack.pop_back() {
+            match action {
+                Action::Spawn(id) => {
+                    if self.sim.remove(id).is_some() {
+                        break;
+                    }
+                }
+                Action::SpawnGroup(ids) => {
+                    let ids: HashSet<u64> = ids.into_iter().collect();
+                    let before = self.sim.bodies.len();
+                    self.sim.bodies.retain(|b| !ids.contains(&b.id));
+                    if self.sim.bodies.len() < before {
+                        break;
+                    }
+                }
+                Action::Merge(merges) => {
+                    let mut split = false;
+                    // Newest first: with substeps, a merged body can merge
+                    // again within the same step
+                    for merge in merges.into_iter().rev() {
+                        if self.sim.remove(merge.result).is_some() {
+                            self.sim.bodies.extend(merge.parts);
+                            split = true;
+                        }
+                    }
+                    if split {
+                        // The parts still overlap and would merge again on
+                        // the next step, so pause to let the user see them.
+                        self.paused = true;
+                        break;
+                    }
+                }
+            }
+        }
+        self.prune_selection();
+        self.sim.compute_accelerations();
+    }
+
+    /// Side panel with live-editable properties of the selected body.
+    fn body_editor(&mut self, ctx: &egui::Context) {
+        if self.replay.is_some() {
+            return;
+        }
+        let Some(index) = self.selected_body.and_then(|id| self.sim.find(id)) else {
+            return;
+        };
+        let mut changed = false;
+        let mut delete = false;
+        let orbit = self.sim.orbit_of(index);
+        egui::SidePanel::right("body_editor").show(ctx, |ui| {
+            let body = &mut self.sim.bodies[index];
+            ui.heading(format!("Body #{}", body.id));
+            let density = body.mass / (body.radius * body.radius);
+            egui::Grid::new("body_editor_grid")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Mass");
+                    let mass_speed = body.mass * 0.01;
+                    let mass = ui.add(
+                        egui::DragValue::new(&mut body.mass)
+                            .speed(mass_speed)
+                            .range(1e-3..=f32::MAX),
+                    );
+                    ui.end_row();
+                    ui.label("Radius");
+                    let radius = ui.add(
+                        egui::DragValue::new(&mut body.radius)
+                            .speed(0.5)
+                            .range(0.1..=1e5),
+                    );
+                    ui.end_row();
+                    if self.edit_lock_density {
+                        if mass.changed() {
+                            body.radius = (body.mass / density).sqrt();
+                        } else if radius.changed() {
+                            body.mass = density * body.radius * body.radius;
+                        }
+                    }
+                    changed |= mass.changed() || radius.changed();
+                    ui.label("Density");
+                    ui.label(format!("{:.3}", body.mass / (body.radius * body.radius)));
+                    ui.end_row();
+
+                    ui.label("Position");
+                    ui.horizontal(|ui| {
+                        changed |= ui.add(egui::DragValue::new(&mut body.pos.x)).changed();
+                        changed |= ui.add(egui::DragValue::new(&mut body.pos.y)).changed();
+                    });
+                    ui.end_row();
+                    ui.label("Velocity");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut body.vel.x).speed(0.1));
+                        ui.add(egui::DragValue::new(&mut body.vel.y).speed(0.1));
+                    });
+                    ui.end_row();
+                    ui.label("Color");
+                    ui.color_edit_button_srgba(&mut body.color);
+                    ui.end_row();
+                    ui.label("Immovable");
+                    if ui.checkbox(&mut body.fixed, "").changed() {
+                        body.vel = Vector2::zeros();
+                        changed = true;
+                    }
+                    ui.end_row();
+                });
+            ui.checkbox(&mut self.edit_lock_density, "Lock density");
+            if let Some(orbit) = orbit {
+                ui.separator();
+                ui.label(format!("Orbiting body #{}", orbit.central));
+                ui.label(orbit.kind.label());
+                ui.label(format!("Eccentricity: {:.3}", orbit.eccentricity));
+                if orbit.kind != OrbitKind::Parabolic {
+                    ui.label(format!("Semi-major axis: {:.1}", orbit.semi_major_axis));
+                }
+                if let Some(period) = orbit.period {
+                    ui.label(format!("Period: {period:.2} s"));
+                }
+            }
+            delete = ui.button("Delete").clicked();
+        });
+        if delete {
+            self.delete_selected();
+        } else if changed {
+            // Mass and position feed the stored accelerations Verlet reuses.
+            self.sim.compute_accelerations();
+        }
+    }
+
+    /// Drops selections whose bodies no longer exist (merged away, culled,
+    /// deleted or undone). The second selection is only meaningful next to
+    /// the primary one, so it goes with it.
+    fn prune_selection(&mut self) {
+        if self
+            .selected_body
+            .is_some_and(|id| self.sim.find(id).is_none())
+        {
+            self.selected_body = None;
+        }
+        if self.selected_body.is_none()
+            || self
+                .second_selected
+                .is_some_and(|id| self.sim.find(id).is_none())
+        {
+            self.second_selected = None;
+        }
+        if !self.multi_selection.is_empty() {
+            let ids: HashSet<u64> = self.sim.bodies.iter().map(|b| b.id).collect();
+            self.multi_selection.retain(|id| ids.contains(id));
+        }
+        if self
+            .orbit_center
+            .is_some_and(|id| self.sim.find(id).is_none())
+        {
+            self.orbit_center = None;
+        }
+    }
+
+    /// Makes `id` the primary selection. A second selection picked next to
+    /// a different primary one is dropped.
+    fn select(&mut self, id: u64) {
+        if self.selected_body != Some(id) {
+            self.second_selected = None;
+        }
+        self.selected_body = Some(id);
+    }
+
+    /// Actions for the body `id` in its right-click menu.
+    fn body_menu(&mut self, ui: &mut egui::Ui, id: u64) {
+        let Some(index) = self.sim.find(id) else {
+            ui.close_menu();
+            return;
+        };
+        ui.label(format!("Body #{id}"));
+        ui.separator();
+        if ui.button("Edit").clicked() {
+            self.select(id);
+            ui.close_menu();
+        }
+        if ui.button("Follow").clicked() {
+            self.select(id);
+            self.follow_selected = true;
+            self.lock_to_com = false;
+            ui.close_menu();
+        }
+        if ui.button("Set as orbit center").clicked() {
+            self.orbit_center = Some(id);
+            self.orbit_mode = true;
+            ui.close_menu();
+        }
+        let body = &mut self.sim.bodies[index];
+        let label = if body.fixed {
+            "Make movable"
+        } else {
+            "Make immovable"
+        };
+        if ui.button(label).clicked() {
+            body.fixed = !body.fixed;
+            body.vel = Vector2::zeros();
+            self.sim.compute_accelerations();
+            ui.close_menu();
+        }
+        if ui.button("Delete").clicked() {
+            self.sim.remove(id);
+            self.sim.compute_accelerations();
+            ui.close_menu();
+        }
+    }
+
+    /// The box-selected bodies, or else the selected one.
+    fn selection(&self) -> Vec<&Body> {
+        let ids = if self.multi_selection.is_empty() {
+            self.selected_body.as_slice()
+        } else {
+            &self.multi_selection
+        };
+        ids.iter()
+            .filter_map(|&id| self.sim.find(id))
+            .map(|index| &self.sim.bodies[index])
+            .collect()
+    }
+
+    /// Puts the selected bodies on the clipboard.
+    fn copy_selection(&mut self, ctx: &egui::Context) {
+        let bodies: Vec<Body> = self.selection().into_iter().cloned().collect();
+        if bodies.is_empty() {
+            self.status_message = Some("Nothing selected to copy".to_owned());
+            return;
+        }
+        ctx.copy_text(Clipboard::to_text(&bodies));
+        self.status_message = Some(format!("Copied {} bodies", bodies.len()));
+    }
+
+    /// Adds copies of the bodies in `text`, centered on `at` with their
+    /// relative positions and velocities kept, and box-selects them.
+    fn paste(&mut self, text: &str, at: Vector2<f32>) {
+        let Some(mut bodies) = Clipboard::from_text(text) else {
+            return;
+        };
+        let centroid = bodies.iter().map(|b| b.pos).sum::<Vector2<f32>>() / bodies.len() as f32;
+        for body in &mut bodies {
+            body.pos += at - centroid;
+        }
+        self.multi_selection = bodies.iter().map(|b| b.id).collect();
+        self.add_generated(bodies);
+    }
+
+    /// Removes the selected body and every box-selected one, by ID, and
+    /// clears the selection.
+    fn delete_selected(&mut self) {
+        if self.replay.is_some() {
+            return;
+        }
+        let mut ids: HashSet<u64> = self.multi_selection.drain(..).collect();
+        ids.extend(self.selected_body.take());
+        self.second_selected = None;
+        if ids.is_empty() {
+            return;
+        }
+        self.sim.bodies.retain(|b| !ids.contains(&b.id));
+        self.sim.compute_accelerations();
+    }
+
+    /// Centers the camera on the followed body. If that body is gone (e.g. it
+    /// was absorbed in a merge) the camera goes back to free movement.
+    fn follow_camera(&mut self) {
+        if self.lock_to_com {
+            if let Some(com) = self.sim.center_of_mass() {
+                self.camera_pos = com - self.frame_origin();
+            }
+            return;
+        }
+        if !self.follow_selected {
+            return;
+        }
+        match self.selected_body.and_then(|id| self.sim.find(id)) {
+            Some(index) => self.camera_pos = self.sim.bodies[index].pos - self.frame_origin(),
+            None => {
+                self.follow_selected = false;
+                self.selected_body = None;
+            }
+        }
+    }
+
+    /// Appends each body's current position to its trail, dropping the oldest
+    /// points beyond `trail_length`.
+    fn record_trails(&mut self) {
+        // Trails are kept relative to the view's frame, so they don't smear
+        // out with the system's drift in the barycentric view.
+        let origin = self.frame_origin();
+        for body in &mut self.sim.bodies {
+            if self.show_trails {
+                body.trail.push_back(body.pos - origin);
+                while body.trail.len() > self.trail_length {
+                    body.trail.pop_front();
+                }
+            } else {
+                body.trail.clear();
+            }
+        }
+    }
+
+    /// Applies this frame's keyboard shortcuts through the key bindings.
+    /// Returns whether a screenshot was requested.
+    fn handle_keys(&mut self, ctx: &egui::Context, input: &egui::InputState, dt: f32) -> bool {
+        let mut take_screenshot = false;
+        if self.bindings.pressed(input, Command::Reset) {
+            self.replay = None;
+            self.stop_recording();
+            self.sim.bodies.clear();
+            self.particles.clear();
+            self.rng = StdRng::seed_from_u64(self.seed);
+            self.undo_stack.clear();
+            self.restart_clock();
+            self.physics_accumulator = 0.0;
+            self.selected_body = None;
+            self.second_selected = None;
+            self.multi_selection.clear();
+            self.ruler.clear();
+            self.potential = None;
             self.camera_pos = Vector2::new(0.0, 0.0);
             self.zoom = 1.0;
         }
-        if input.key_pressed(egui::Key::H) {
+        if self.bindings.pressed(input, Command::Screenshot) {
+            take_screenshot = true;
+        }
+        if self.bindings.pressed(input, Command::ToggleHud) {
             self.show_hud = !self.show_hud;
         }
-        if input.key_pressed(egui::Key::E) {
-            self.elastic = !self.elastic;
+        if self.bindings.pressed(input, Command::ToggleCollisionMode) {
+            self.sim.collision_mode = match self.sim.collision_mode {
+                CollisionMode::Bounce => CollisionMode::Merge,
+                CollisionMode::Merge => CollisionMode::Bounce,
+            };
+        }
+        if self.bindings.pressed(input, Command::Deselect) {
+            self.selected_body = None;
+            self.second_selected = None;
+            self.multi_selection.clear();
+        }
+        if input.modifiers.command
+            && self.bindings.pressed(input, Command::Undo)
+            && self.replay.is_none()
+        {
+            self.undo();
+        }
+        if self.replay.is_none() {
+            for event in &input.events {
+                match event {
+                    egui::Event::Copy => self.copy_selection(ctx),
+                    egui::Event::Paste(text) => {
+                        let at = input
+                            .pointer
+                            .hover_pos()
+                            .map_or_else(|| self.view_center(), |p| self.screen_to_world(p));
+                        self.paste(text, at);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if self.bindings.pressed(input, Command::DeleteSelected) {
+            self.delete_selected();
+        }
+        if self.bindings.pressed(input, Command::FitView) {
+            self.fit_view();
+        }
+        if self.bindings.pressed(input, Command::SpawnCluster)
+            && self.replay.is_none()
+            && let Some(hover) = input.pointer.hover_pos()
+        {
+            let cluster = generators::gaussian_cluster(
+                self.screen_to_world(hover),
+                &self.cluster_params,
+                &mut self.rng,
+            );
+            self.add_generated(cluster);
+        }
+        if self.bindings.pressed(input, Command::ToggleMeasureMode) {
+            self.measure_mode = !self.measure_mode;
+            self.ruler.clear();
+        }
+        if self.bindings.pressed(input, Command::ToggleOrbitMode) {
+            self.orbit_mode = !self.orbit_mode;
+        }
+        if self.bindings.pressed(input, Command::FollowSelected) {
+            self.follow_selected = !self.follow_selected;
+            self.lock_to_com &= !self.follow_selected;
+        }
+        if self.bindings.pressed(input, Command::LockToCenterOfMass) {
+            self.lock_to_com = !self.lock_to_com;
+            self.follow_selected &= !self.lock_to_com;
+        }
+        if self.bindings.pressed(input, Command::Pause) {
+            self.paused = !self.paused;
+            self.paused_steps = 0;
+        }
+        let time_scale = if self.bindings.pressed(input, Command::Slower) {
+            Some(self.time_scale / TIME_SCALE_STEP)
+        } else if self.bindings.pressed(input, Command::Faster) {
+            Some(self.time_scale * TIME_SCALE_STEP)
+        } else if self.bindings.pressed(input, Command::RealTime) {
+            Some(1.0)
+        } else {
+            None
+        };
+        if let Some(scale) = time_scale {
+            self.time_scale = scale.clamp(*TIME_SCALE_RANGE.start(), *TIME_SCALE_RANGE.end());
+            self.time_scale_changed_at = Some(input.time);
+        }
+        if self.paused && self.replay.is_none() && self.bindings.pressed(input, Command::Step) {
+            self.step_physics(self.next_step_dt());
+            self.paused_steps += 1;
+        }
+
+        // Nudge the selected body while paused: arrows move it, Shift+arrows
+        // change its velocity, both by a fixed on-screen amount
+        let nudge = [
+            (Command::NudgeLeft, -Vector2::x()),
+            (Command::NudgeRight, Vector2::x()),
+            (Command::NudgeUp, -Vector2::y()),
+            (Command::NudgeDown, Vector2::y()),
+        ]
+        .into_iter()
+        .filter(|&(command, _)| self.bindings.pressed(input, command))
+        .map(|(_, dir)| dir)
+        .sum::<Vector2<f32>>();
+        if nudge != Vector2::zeros()
+            && self.paused
+            && self.replay.is_none()
+            && let Some(index) = self.selected_body.and_then(|id| self.sim.find(id))
+        {
+            let body = &mut self.sim.bodies[index];
+            if input.modifiers.shift {
+                body.vel += nudge * (NUDGE_SPEED_PIXELS / self.zoom);
+            } else {
+                body.pos += nudge * (NUDGE_PIXELS / self.zoom);
+            }
+            self.sim.compute_accelerations();
         }
 
         // Pan
         let pan_speed = 300.0 * dt / self.zoom;
-        if input.key_down(egui::Key::W) {
+        if self.bindings.down(input, Command::PanUp) {
             self.camera_pos.y -= pan_speed;
         }
-        if input.key_down(egui::Key::S) {
+        if self.bindings.down(input, Command::PanDown) {
             self.camera_pos.y += pan_speed;
         }
-        if input.key_down(egui::Key::A) {
+        if self.bindings.down(input, Command::PanLeft) {
             self.camera_pos.x -= pan_speed;
         }
-        if input.key_down(egui::Key::D) {
+        if self.bindings.down(input, Command::PanRight) {
             self.camera_pos.x += pan_speed;
         }
+        take_screenshot
+    }
+
+    /// While waiting for a key to rebind a command to, takes the first key
+    /// pressed (Escape cancels). Returns whether keys were being captured, so
+    /// the press doesn't also trigger a shortcut.
+    fn capture_rebinding(&mut self, input: &egui::InputState) -> bool {
+        let Some(command) = self.rebinding else {
+            return false;
+        };
+        let pressed = input.events.iter().find_map(|event| match event {
+            egui::Event::Key {
+                key, pressed: true, ..
+            } => Some(*key),
+            _ => None,
+        });
+        if let Some(key) = pressed {
+            if key != egui::Key::Escape {
+                self.bindings.set(command, key);
+            }
+            self.rebinding = None;
+        }
+        true
+    }
+
+    /// Lists every command with a button to rebind its key.
+    fn key_bindings_ui(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("key_bindings")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for command in Command::ALL {
+                    ui.label(command.label());
+                    let text = if self.rebinding == Some(command) {
+                        "Press a key…"
+                    } else {
+                        self.bindings.key(command).name()
+                    };
+                    if ui.button(text).clicked() {
+                        self.rebinding = Some(command);
+                    }
+                    ui.end_row();
+                }
+            });
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                self.status_message = Some(match self.bindings.save(Path::new(KEYBINDINGS_PATH)) {
+                    Ok(()) => format!("Saved {KEYBINDINGS_PATH}"),
+                    Err(err) => format!("Saving key bindings failed: {err}"),
+                });
+            }
+            if ui.button("Defaults").clicked() {
+                self.bindings = KeyBindings::default();
+                self.rebinding = None;
+            }
+        });
+    }
+
+    /// The Controls help, listing the current key for each command.
+    fn controls_help(&self) -> String {
+        let mut help = "Controls:\n".to_owned();
+        for command in Command::ALL {
+            help += &format!(
+                "{}: {}\n",
+                self.bindings.key(command).name(),
+                command.label()
+            );
+        }
+        help += "Middle-Right Drag: Pan\n\
+            Scroll: Zoom (while aiming: Resize)\n\
+            Click: Select\n\
+            Shift+Click: Select Second Body\n\
+            Ctrl+Drag: Box Select\n\
+            Drag Body: Move (paused)\n\
+            Right-Click Body: Actions\n\
+            Ctrl+C / Ctrl+V: Copy / Paste Selected\n\
+            Click-Drag: Spawn";
+        help
+    }
+}
+
+/// Zoom after scrolling by `scroll` points from `zoom`, kept inside
+/// [`ZOOM_RANGE`].
+fn scrolled_zoom(zoom: f32, scroll: f32) -> f32 {
+    let max_step = MAX_ZOOM_STEP.ln();
+    let step = (scroll * ZOOM_SENSITIVITY).clamp(-max_step, max_step).exp();
+    (zoom * step).clamp(*ZOOM_RANGE.start(), *ZOOM_RANGE.end())
+}
+
+fn nalgebra_from_vec2(v: Vec2) -> Vector2<f32> {
+    Vector2::new(v.x, v.y)
+}
+
+/// Converts a world-space vector to a screen-space arrow, clamped so very
+/// large vectors don't fill the screen.
+fn arrow_vec(v: Vector2<f32>, scale: f32) -> Vec2 {
+    let arrow = Vec2::new(v.x, v.y) * scale;
+    if arrow.length() > MAX_ARROW_LEN {
+        arrow.normalized() * MAX_ARROW_LEN
+    } else {
+        arrow
+    }
+}
+
+impl App for GravisimApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
+        let input = ctx.input(|i| i.clone());
+        let dt = input.stable_dt;
+
+        // Screenshots arrive a frame or more after they are requested
+        for event in &input.events {
+            if let egui::Event::Screenshot {
+                image, user_data, ..
+            } = event
+            {
+                let view = user_data
+                    .data
+                    .as_ref()
+                    .and_then(|data| data.downcast_ref::<egui::Rect>())
+                    .copied()
+                    .unwrap_or(self.viewport);
+                self.save_screenshot(image, view, input.pixels_per_point);
+            }
+        }
+        let mut take_screenshot = std::mem::take(&mut self.screenshot_requested);
+        self.record_frame_time(input.unstable_dt);
+
+        // Handle input
+        if !self.capture_rebinding(&input) && !ctx.wants_keyboard_input() {
+            take_screenshot |= self.handle_keys(ctx, &input, dt);
+        }
+        if input.pointer.button_down(egui::PointerButton::Middle)
+            || input.pointer.button_down(egui::PointerButton::Secondary)
+        {
+            self.camera_pos -= nalgebra_from_vec2(input.pointer.delta()) / self.zoom;
+        }
+
+        // Physics
+        if self.replay.is_some() {
+            self.advance_replay(dt);
+        } else if !self.paused {
+            self.advance_physics(dt);
+            self.record_trails();
+        }
+        self.follow_camera();
+        self.prune_selection();
+        effects::update(&mut self.particles, dt);
+
+        if self.show_hud || self.show_energy_plot {
+            self.refresh_energy(dt);
+        }
 
-        // Zoom
-        self.zoom *= (1.0 + input.raw_scroll_delta.y * 0.1).clamp(0.1, 10.0);
+        self.body_editor(ctx);
+        self.replay_bar(ctx);
+        self.energy_plot(ctx);
 
         egui::CentralPanel::default().show(ctx, |ui| {
-            let (rect, _) =
+            let (rect, response) =
                 ui.allocate_exact_size(ui.available_size(), egui::Sense::click_and_drag());
             let painter = ui.painter();
             painter.rect_filled(rect, 0.0, Color32::BLACK);
+            if self.show_starfield {
+                let offset = Vec2::new(self.camera_pos.x, self.camera_pos.y) * STARFIELD_PARALLAX;
+                painter.add(self.starfield.mesh(rect, offset));
+            }
 
+            self.viewport = rect;
             let center = rect.center();
             let center_vec = nalgebra_from_vec2(center.to_vec2());
 
-            // Gravity
-            for i in 0..self.bodies.len() {
-                let (left, right) = self.bodies.split_at_mut(i + 1);
-                let (this, others) = left.split_last_mut().unwrap();
-                for other in right {
-                    this.apply_gravity(other);
+            // Scrolling while aiming a new body resizes it; otherwise it zooms,
+            // keeping the world point under the cursor fixed on screen.
+            // Scrolling over a window drawn on top (the HUD, plots) scrolls
+            // that window instead.
+            let hover_on_scene = input
+                .pointer
+                .hover_pos()
+                .is_some_and(|p| rect.contains(p) && ctx.layer_id_at(p) == Some(ui.layer_id()));
+            let scroll = if hover_on_scene {
+                input.raw_scroll_delta.y
+            } else {
+                0.0
+            };
+            if self.selected_pos.is_some() {
+                let size = self.selected_size * (scroll * ZOOM_SENSITIVITY).exp();
+                self.selected_size = size.clamp(*SIZE_RANGE.start(), *SIZE_RANGE.end());
+            } else {
+                let old_zoom = self.zoom;
+                self.zoom = scrolled_zoom(self.zoom, scroll);
+                if let Some(hover) = input.pointer.hover_pos() {
+                    let offset = nalgebra_from_vec2(hover.to_vec2()) - center_vec;
+                    self.camera_pos += offset * (1.0 / old_zoom - 1.0 / self.zoom);
                 }
             }
-            // for i in 0..self.bodies.len() {
-            //     for j in 0..self.bodies.len() {
-            //         if i == j {
-            //             continue;
-            //         }
-            //         let other = &self.bodies[j];
-            //         self.bodies[i].apply_gravity(other);
-            //     }
-            // }
 
-            // Update
-            for body in &mut self.bodies {
-                body.update(dt);
+            // Presses that land on a window drawn over the view (the HUD,
+            // the body editor, plots) belong to that window, not the scene
+            let press_on_ui = input
+                .pointer
+                .press_origin()
+                .is_some_and(|p| !rect.contains(p) || ctx.layer_id_at(p) != Some(ui.layer_id()));
+
+            // The minimap covers the bottom-right corner; clicking or dragging
+            // on it moves the camera there instead of interacting with bodies
+            let minimap = self
+                .scene_bounds()
+                .filter(|_| self.show_minimap)
+                .map(|(min, max)| {
+                    let map_rect = egui::Rect::from_min_size(
+                        rect.right_bottom() - Vec2::splat(MINIMAP_SIZE + SCALE_BAR_MARGIN),
+                        Vec2::splat(MINIMAP_SIZE),
+                    );
+                    render::Minimap::new(map_rect.shrink(4.0), min, max)
+                });
+            let press_on_minimap = !press_on_ui
+                && minimap.as_ref().is_some_and(|map| {
+                    input
+                        .pointer
+                        .press_origin()
+                        .is_some_and(|p| map.rect.expand(4.0).contains(p))
+                });
+            if press_on_minimap
+                && input.pointer.primary_down()
+                && let Some(map) = &minimap
+                && let Some(pointer) = input.pointer.interact_pos()
+            {
+                self.camera_pos = map.to_world(map.rect.clamp(pointer)) - self.frame_origin();
             }
 
+            // Locals in world space from here on
+            let frame_origin = self.frame_origin();
+            let camera_pos = self.view_center();
+            let zoom = self.zoom;
+            let to_screen = |world: Vector2<f32>| {
+                let screen_vec = (world - camera_pos) * zoom + center_vec;
+                Pos2::new(screen_vec.x, screen_vec.y)
+            };
+
             // Mouse world pos
             let mouse_pos = input.pointer.hover_pos().unwrap_or(center);
             let mouse_vec = nalgebra_from_vec2(mouse_pos.to_vec2());
-            let world_mouse = (mouse_vec - center_vec) / self.zoom + self.camera_pos;
-
-            // Handle placing body
-            if input.pointer.any_pressed() && self.selected_pos.is_none() {
-                self.selected_pos = Some(world_mouse);
-            }
-
-            if input.pointer.any_released() {
-                if let Some(start) = self.selected_pos.take() {
-                    let end = world_mouse;
-                    let vel = (end - start) / 20.0;
-                    self.bodies.push(Body::new(
-                        start,
-                        vel,
-                        self.selected_density,
-                        self.selected_size,
-                    ));
+            let world_mouse = (mouse_vec - center_vec) / self.zoom + camera_pos;
+
+            // Clicking a body selects it (and, while paused, starts dragging
+            // it); clicking empty space starts a spawn
+            let press_on_scene = !press_on_ui && !press_on_minimap;
+            if self.measure_mode && input.pointer.primary_pressed() && press_on_scene {
+                if self.ruler.len() == 2 {
+                    self.ruler.clear();
+                }
+                let anchor = match self.sim.body_at(world_mouse, PICK_RADIUS / zoom) {
+                    Some(index) => Anchor::Body(self.sim.bodies[index].id),
+                    None => Anchor::Point(world_mouse),
+                };
+                self.ruler.push(anchor);
+            } else if input.pointer.primary_pressed() && input.modifiers.command && press_on_scene {
+                self.box_select = Some(world_mouse);
+            } else if input.pointer.primary_pressed()
+                && self.selected_pos.is_none()
+                && press_on_scene
+            {
+                match self.sim.body_at(world_mouse, PICK_RADIUS / zoom) {
+                    Some(index)
+                        if input.modifiers.shift
+                            && self
+                                .selected_body
+                                .is_some_and(|id| id != self.sim.bodies[index].id) =>
+                    {
+                        self.second_selected = Some(self.sim.bodies[index].id);
+                    }
+                    Some(index) => {
+                        let body = &self.sim.bodies[index];
+                        self.selected_body = Some(body.id);
+                        self.second_selected = None;
+                        if self.paused && self.replay.is_none() {
+                            self.dragging_body = Some(BodyDrag {
+                                id: body.id,
+                                grab_offset: body.pos - world_mouse,
+                                start: world_mouse,
+                            });
+                        }
+                    }
+                    None => {
+                        self.selected_body = None;
+                        self.second_selected = None;
+                        if self.replay.is_none() {
+                            self.selected_pos = Some(world_mouse);
+                        }
+                    }
+                }
+            }
+
+            if let Some(start) = self.box_select
+                && input.pointer.primary_released()
+            {
+                let (min, max) = (start.inf(&world_mouse), start.sup(&world_mouse));
+                self.multi_selection = self
+                    .sim
+                    .bodies
+                    .iter()
+                    .filter(|b| {
+                        (min.x..=max.x).contains(&b.pos.x) && (min.y..=max.y).contains(&b.pos.y)
+                    })
+                    .map(|b| b.id)
+                    .collect();
+                self.box_select = None;
+            }
+
+            if let Some(drag) = &self.dragging_body
+                && let Some(index) = self.sim.find(drag.id)
+            {
+                let fling = self.drag_velocity(drag.start, world_mouse);
+                let body = &mut self.sim.bodies[index];
+                body.pos = world_mouse + drag.grab_offset;
+                if input.pointer.primary_released() {
+                    match self.drop_velocity {
+                        DropVelocity::Keep => {}
+                        DropVelocity::Zero => body.vel = Vector2::zeros(),
+                        DropVelocity::Fling => body.vel = fling,
+                    }
+                    self.sim.compute_accelerations();
+                }
+            }
+            if input.pointer.primary_released() {
+                self.dragging_body = None;
+            }
+
+            if input.pointer.primary_released()
+                && let Some(start) = self.selected_pos.take()
+            {
+                let vel = self.spawn_velocity(start, world_mouse);
+                let color = self.next_body_color();
+                let body = Body::new(start, vel, self.selected_density, self.selected_size, color);
+                let com = self.sim.center_of_mass().unwrap_or_else(Vector2::zeros);
+                let com_vel = self
+                    .sim
+                    .center_of_mass_velocity()
+                    .unwrap_or_else(Vector2::zeros);
+                let mirror = self
+                    .symmetry
+                    .mirror(start, com)
+                    .zip(self.symmetry.mirror(vel, com_vel));
+                match mirror {
+                    Some((pos, vel)) => {
+                        let image =
+                            Body::new(pos, vel, self.selected_density, self.selected_size, color);
+                        self.push_undo(Action::SpawnGroup(vec![body.id, image.id]));
+                        self.sim.bodies.extend([body, image]);
+                    }
+                    None => {
+                        self.push_undo(Action::Spawn(body.id));
+                        self.sim.bodies.push(body);
+                    }
+                }
+                self.sim.compute_accelerations();
+            }
+
+            // Render the potential heatmap, resampled on a coarse screen grid
+            // a few times a second
+            if self.show_potential {
+                self.potential_age += dt;
+                if self.potential_age >= POTENTIAL_REFRESH_INTERVAL {
+                    let bodies = self.sim.bodies.len().max(1) as f32;
+                    let cell =
+                        POTENTIAL_CELL.max((rect.area() * bodies / POTENTIAL_BUDGET as f32).sqrt());
+                    let to_world = |p: Pos2| {
+                        (nalgebra_from_vec2(p.to_vec2()) - center_vec) / zoom + camera_pos
+                    };
+                    self.potential = Some(PotentialMap::sample(rect, cell, |centers| {
+                        let points: Vec<Vector2<f32>> =
+                            centers.iter().map(|&p| to_world(p)).collect();
+                        self.sim.potentials_at(&points)
+                    }));
+                    self.potential_age = 0.0;
+                }
+                if let Some(potential) = &self.potential
+                    && !self.sim.bodies.is_empty()
+                {
+                    painter.add(potential.mesh());
+                }
+            } else {
+                self.potential = None;
+                self.potential_age = f32::INFINITY;
+            }
+
+            // Render the world grid, under everything else
+            if self.show_grid {
+                let spacing = render::grid_spacing(zoom, MIN_GRID_SPACING);
+                let half_extent = nalgebra_from_vec2(rect.size()) / (2.0 * zoom);
+                let min = camera_pos - half_extent;
+                let max = camera_pos + half_extent;
+                let stroke = (1.0, Color32::from_gray(40));
+                // Lines are indexed rather than accumulated so float error
+                // can't stall the loop far from the origin
+                let lines = |min: f32, max: f32| {
+                    ((min / spacing).ceil() as i64..=(max / spacing).floor() as i64)
+                        .map(move |i| i as f32 * spacing)
+                };
+                for x in lines(min.x, max.x) {
+                    let screen_x = to_screen(Vector2::new(x, 0.0)).x;
+                    painter.vline(screen_x, rect.y_range(), stroke);
+                }
+                for y in lines(min.y, max.y) {
+                    let screen_y = to_screen(Vector2::new(0.0, y)).y;
+                    painter.hline(rect.x_range(), screen_y, stroke);
+                }
+            }
+
+            // Render the force field on a sparse grid. Arrow lengths follow the
+            // log of the field strength so the region around a heavy body
+            // doesn't dwarf everything else
+            if self.show_field && !self.sim.bodies.is_empty() {
+                let bodies = self.sim.bodies.len() as f32;
+                let spacing =
+                    FIELD_ARROW_SPACING.max((rect.area() * bodies / FIELD_BUDGET as f32).sqrt());
+                let cols = (rect.width() / spacing) as usize;
+                let rows = (rect.height() / spacing) as usize;
+                let margin = (rect.size() - Vec2::new(cols as f32, rows as f32) * spacing) * 0.5;
+                let samples: Vec<(Pos2, Vector2<f32>)> = (0..rows)
+                    .flat_map(|row| (0..cols).map(move |col| (row, col)))
+                    .map(|(row, col)| {
+                        let screen = rect.min
+                            + margin
+                            + Vec2::new(col as f32 + 0.5, row as f32 + 0.5) * spacing;
+                        let world = self.screen_to_world(screen);
+                        (screen, self.sim.field_at(world))
+                    })
+                    .collect();
+                let log_strength = |a: &Vector2<f32>| a.norm().max(f32::MIN_POSITIVE).ln();
+                let (min, max) = samples
+                    .iter()
+                    .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), (_, a)| {
+                        (lo.min(log_strength(a)), hi.max(log_strength(a)))
+                    });
+                let range = (max - min).max(f32::EPSILON);
+                for (screen, acc) in &samples {
+                    let t = (log_strength(acc) - min) / range;
+                    let len = spacing * (0.15 + 0.7 * t);
+                    let dir = acc.try_normalize(0.0).unwrap_or_else(Vector2::zeros);
+                    painter.arrow(
+                        *screen - Vec2::new(dir.x, dir.y) * (len * 0.5),
+                        Vec2::new(dir.x, dir.y) * len,
+                        (
+                            1.0,
+                            Color32::from_rgb(90, 160, 120).gamma_multiply(0.4 + 0.6 * t),
+                        ),
+                    );
+                }
+            }
+
+            // Render the world rectangle in bounded modes
+            if self.sim.boundary != Boundary::Open {
+                let half = self.sim.world_size * 0.5;
+                painter.rect_stroke(
+                    egui::Rect::from_two_pos(to_screen(-half), to_screen(half)),
+                    0.0,
+                    (1.5, Color32::from_rgb(120, 120, 60)),
+                    egui::StrokeKind::Middle,
+                );
+            }
+
+            // Render trails, fading from the body color to transparent
+            if self.show_trails {
+                for body in &self.sim.bodies {
+                    let len = body.trail.len() as f32;
+                    let points = body.trail.iter().map(|&p| to_screen(p + frame_origin));
+                    for (i, (a, b)) in points.clone().zip(points.skip(1)).enumerate() {
+                        let fade = (i + 1) as f32 / len;
+                        painter.line_segment([a, b], (1.0, body.color.gamma_multiply(fade)));
+                    }
+                }
+            }
+
+            let colors = BodyColors::new(self.color_mode, &self.sim.bodies);
+            let frame_velocity = if self.barycentric_view && self.barycentric_velocities {
+                self.sim
+                    .center_of_mass_velocity()
+                    .unwrap_or_else(Vector2::zeros)
+            } else {
+                Vector2::zeros()
+            };
+
+            // Render comet tails behind fast bodies, tapering from the body's
+            // width to a transparent point pointing away from its motion
+            if self.show_comet_tails {
+                let mut tails = egui::Mesh::default();
+                for body in &self.sim.bodies {
+                    let vel = body.vel - frame_velocity;
+                    let speed = vel.norm();
+                    if speed <= self.comet_speed {
+                        continue;
+                    }
+                    let head = to_screen(body.pos);
+                    let length =
+                        ((speed - self.comet_speed) * COMET_TAIL_TIME * zoom).min(MAX_COMET_TAIL);
+                    let width = (body.radius * zoom).max(POINT_SIZE * 0.5);
+                    if !rect.expand(length + width).contains(head) {
+                        continue;
+                    }
+                    let back = -Vec2::new(vel.x, vel.y) / speed;
+                    let side = back.rot90() * width;
+                    let color = colors.color(body);
+                    let first = tails.vertices.len() as u32;
+                    tails.colored_vertex(head + side, color.gamma_multiply(0.6));
+                    tails.colored_vertex(head - side, color.gamma_multiply(0.6));
+                    tails.colored_vertex(head + back * length, Color32::TRANSPARENT);
+                    tails.add_triangle(first, first + 1, first + 2);
+                }
+                painter.add(tails);
+            }
+
+            // Render glow halos behind massive bodies as stacked translucent
+            // disks: heavier bodies glow wider and brighter
+            if self.show_glow {
+                for body in &self.sim.bodies {
+                    if body.mass < self.glow_mass {
+                        continue;
+                    }
+                    let boost = (body.mass / self.glow_mass).log10().min(GLOW_MAX_BOOST);
+                    let glow_radius = body.radius * zoom * (2.0 + boost);
+                    let screen_pos = to_screen(body.pos);
+                    if !rect.expand(glow_radius).contains(screen_pos) {
+                        continue;
+                    }
+                    let alpha = GLOW_ALPHA * (1.0 + boost) / GLOW_RINGS as f32;
+                    let color = colors.color(body).gamma_multiply(alpha);
+                    for ring in 0..GLOW_RINGS {
+                        let t = 1.0 - ring as f32 / GLOW_RINGS as f32;
+                        painter.circle_filled(screen_pos, glow_radius * t, color);
+                    }
                 }
             }
 
             // Render bodies
-            for body in &self.bodies {
-                let screen_vec = (body.pos - self.camera_pos) * self.zoom + center_vec;
-                let screen_pos = Pos2::new(screen_vec.x, screen_vec.y);
-                painter.circle_filled(screen_pos, body.radius * self.zoom, body.color);
+            // Sub-pixel bodies are batched into one mesh of tiny quads rather
+            // than tessellated as individual circles; larger ones join a mesh
+            // of disks unless the per-shape path is chosen for comparison
+            let mut points = egui::Mesh::default();
+            let mut disks = egui::Mesh::default();
+            for body in &self.sim.bodies {
+                let screen_pos = to_screen(body.pos);
+                let screen_radius = body.radius * zoom;
+                // Skip bodies whose bounding circle lies entirely off-screen
+                if !rect.expand(screen_radius).contains(screen_pos) {
+                    continue;
+                }
+                if screen_radius < POINT_LOD_RADIUS {
+                    points.add_colored_rect(
+                        egui::Rect::from_center_size(screen_pos, Vec2::splat(POINT_SIZE)),
+                        colors.color(body),
+                    );
+                } else if self.mesh_bodies {
+                    render::add_disk(&mut disks, screen_pos, screen_radius, colors.color(body));
+                } else {
+                    painter.circle_filled(screen_pos, screen_radius, colors.color(body));
+                }
+            }
+            painter.add(points);
+            painter.add(disks);
+
+            // Render merge particles as small fading quads
+            if !self.particles.is_empty() {
+                let mut sparks = egui::Mesh::default();
+                for particle in &self.particles {
+                    let pos = to_screen(particle.pos);
+                    if rect.contains(pos) {
+                        sparks.add_colored_rect(
+                            egui::Rect::from_center_size(pos, Vec2::splat(PARTICLE_SIZE)),
+                            particle.color.gamma_multiply(particle.fade()),
+                        );
+                    }
+                }
+                painter.add(sparks);
+            }
+
+            // Render labels beside on-screen bodies, up to a budget
+            if self.body_label != BodyLabel::None {
+                let visible = self
+                    .sim
+                    .bodies
+                    .iter()
+                    .map(|body| (body, to_screen(body.pos)))
+                    .filter(|(_, pos)| rect.contains(*pos))
+                    .take(MAX_LABELS);
+                for (body, pos) in visible {
+                    if let Some(text) = self.body_label.text(body) {
+                        painter.text(
+                            pos + Vec2::new(body.radius * zoom + 3.0, 0.0),
+                            egui::Align2::LEFT_CENTER,
+                            text,
+                            egui::FontId::proportional(11.0),
+                            Color32::LIGHT_GRAY,
+                        );
+                    }
+                }
+            }
+
+            // Render velocity vectors
+            if self.show_velocity {
+                let scale = self.velocity_arrow_scale * zoom;
+                for body in &self.sim.bodies {
+                    painter.arrow(
+                        to_screen(body.pos),
+                        arrow_vec(body.vel - frame_velocity, scale),
+                        (1.5, Color32::YELLOW),
+                    );
+                }
+            }
+
+            // Render net gravitational acceleration
+            if self.show_acceleration {
+                let scale = self.acceleration_arrow_scale * zoom;
+                for body in &self.sim.bodies {
+                    painter.arrow(
+                        to_screen(body.pos),
+                        arrow_vec(body.acc, scale),
+                        (1.5, Color32::RED),
+                    );
+                }
+            }
+
+            // Render tidal axes as segments centered on each body, as long as
+            // the tidal pull across it; big scenes only get the first bodies
+            // within the per-frame budget of pair evaluations
+            if self.show_tidal {
+                let scale = self.tidal_scale * zoom;
+                let limit = TIDAL_BUDGET / self.sim.bodies.len().max(1);
+                let visible = (0..self.sim.bodies.len())
+                    .filter(|&i| rect.contains(to_screen(self.sim.bodies[i].pos)))
+                    .take(limit);
+                for index in visible {
+                    let body = &self.sim.bodies[index];
+                    if let Some((axis, rate)) = self.sim.tidal_stretch(index) {
+                        let half = arrow_vec(axis * rate * body.radius, scale) * 0.5;
+                        let pos = to_screen(body.pos);
+                        painter.line_segment(
+                            [pos - half, pos + half],
+                            (1.5, Color32::from_rgb(255, 100, 255)),
+                        );
+                    }
+                }
+            }
+
+            // Render center of mass
+            if self.show_center_of_mass
+                && let Some(com) = self.sim.center_of_mass()
+            {
+                let p = to_screen(com);
+                let stroke = (1.5, Color32::ORANGE);
+                painter.line_segment(
+                    [p - Vec2::X * CROSSHAIR_SIZE, p + Vec2::X * CROSSHAIR_SIZE],
+                    stroke,
+                );
+                painter.line_segment(
+                    [p - Vec2::Y * CROSSHAIR_SIZE, p + Vec2::Y * CROSSHAIR_SIZE],
+                    stroke,
+                );
+            }
+
+            // Outline the selected body's Roche limit around its primary
+            if self.show_roche_limit
+                && let Some(index) = self.selected_body.and_then(|id| self.sim.find(id))
+                && let Some((primary, limit)) = self.sim.roche_limit_of(index)
+            {
+                let center = self.sim.bodies[primary].pos;
+                let circle: Vec<Pos2> = (0..=ROCHE_SEGMENTS)
+                    .map(|i| {
+                        let angle = i as f32 / ROCHE_SEGMENTS as f32 * std::f32::consts::TAU;
+                        to_screen(center + Vector2::new(angle.cos(), angle.sin()) * limit)
+                    })
+                    .collect();
+                painter.extend(egui::Shape::dashed_line(
+                    &circle,
+                    (1.0, Color32::from_rgb(255, 140, 80)),
+                    6.0,
+                    4.0,
+                ));
             }
 
-            // Render selected circle
-            if let Some(_) = self.selected_pos {
-                let screen_vec = (world_mouse - self.camera_pos) * self.zoom + center_vec;
-                let screen_pos = Pos2::new(screen_vec.x, screen_vec.y);
+            // Highlight the selected body with a pulsing ring, drawn over a
+            // dark outline so it stands out against bright bodies too
+            if let Some(index) = self.selected_body.and_then(|id| self.sim.find(id)) {
+                let body = &self.sim.bodies[index];
+                let phase = (input.time * SELECTION_PULSE_RATE * std::f64::consts::TAU).sin();
+                let pulse = 0.5 + 0.5 * phase as f32;
+                let radius = body.radius * zoom + SELECTION_RING_GAP + pulse * 2.0;
+                painter.circle_stroke(to_screen(body.pos), radius, (4.0, Color32::BLACK));
                 painter.circle_stroke(
-                    screen_pos,
-                    self.selected_size * self.zoom,
-                    (1.0, Color32::LIGHT_GREEN),
+                    to_screen(body.pos),
+                    radius,
+                    (1.5 + pulse * 1.5, Color32::from_rgb(255, 255, 120)),
+                );
+            }
+            if let Some(index) = self.second_selected.and_then(|id| self.sim.find(id)) {
+                let body = &self.sim.bodies[index];
+                painter.circle_stroke(
+                    to_screen(body.pos),
+                    body.radius * zoom + SELECTION_RING_GAP,
+                    (1.5, Color32::LIGHT_BLUE),
+                );
+            }
+
+            // Highlight the box selection, and the box being dragged out
+            for index in self
+                .multi_selection
+                .iter()
+                .filter_map(|&id| self.sim.find(id))
+            {
+                let body = &self.sim.bodies[index];
+                painter.circle_stroke(
+                    to_screen(body.pos),
+                    body.radius * zoom + SELECTION_RING_GAP,
+                    (1.0, Color32::from_rgb(120, 255, 160)),
+                );
+            }
+            if let Some(start) = self.box_select {
+                let area = egui::Rect::from_two_pos(to_screen(start), to_screen(world_mouse));
+                painter.rect_filled(
+                    area,
+                    0.0,
+                    Color32::from_rgba_unmultiplied(120, 255, 160, 24),
+                );
+                painter.rect_stroke(
+                    area,
+                    0.0,
+                    (1.0, Color32::from_rgb(120, 255, 160)),
+                    egui::StrokeKind::Middle,
+                );
+            }
+
+            // Render the predicted path of the body being aimed
+            if let Some(start) = self.selected_pos {
+                let n = self.sim.bodies.len() + 1;
+                let steps = (PREDICTION_BUDGET / (n * n)).min(PREDICTION_STEPS);
+                let path = self.sim.predict_path(
+                    start,
+                    self.spawn_velocity(start, world_mouse),
+                    self.selected_density,
+                    self.selected_size,
+                    steps,
+                    PREDICTION_DT,
+                );
+                painter.add(egui::Shape::line(
+                    path.into_iter().map(to_screen).collect(),
+                    (1.0, Color32::LIGHT_GREEN.gamma_multiply(0.4)),
+                ));
+            }
+
+            // Render selected circle with its size, and where its mirror image
+            // will appear
+            if let Some(start) = self.selected_pos {
+                let radius = self.selected_size * self.zoom;
+                painter.circle_stroke(to_screen(world_mouse), radius, (1.0, Color32::LIGHT_GREEN));
+                painter.text(
+                    to_screen(world_mouse) + Vec2::new(radius + 4.0, 0.0),
+                    egui::Align2::LEFT_CENTER,
+                    format!("r = {:.1}", self.selected_size),
+                    egui::FontId::proportional(12.0),
+                    Color32::LIGHT_GREEN,
+                );
+                let com = self.sim.center_of_mass().unwrap_or_else(Vector2::zeros);
+                if let Some(image) = self.symmetry.mirror(start, com) {
+                    painter.circle_stroke(
+                        to_screen(image),
+                        self.selected_size * self.zoom,
+                        (1.0, Color32::LIGHT_GREEN.gamma_multiply(0.5)),
+                    );
+                }
+            }
+
+            // Right-clicking a body opens a menu of per-body actions
+            if response.secondary_clicked() {
+                self.context_body = self
+                    .sim
+                    .body_at(world_mouse, PICK_RADIUS / zoom)
+                    .map(|index| self.sim.bodies[index].id);
+            }
+            if let Some(id) = self.context_body
+                && self.replay.is_none()
+            {
+                response.context_menu(|ui| self.body_menu(ui, id));
+            }
+
+            // Describe the body under the pointer, unless something is being
+            // dragged
+            let over_minimap = minimap
+                .as_ref()
+                .is_some_and(|map| map.rect.expand(4.0).contains(mouse_pos));
+            if self.selected_pos.is_none()
+                && self.dragging_body.is_none()
+                && !over_minimap
+                && !take_screenshot
+                && let Some(index) = self.sim.body_at(world_mouse, PICK_RADIUS / zoom)
+            {
+                let body = &self.sim.bodies[index];
+                response.on_hover_ui_at_pointer(|ui| {
+                    ui.label(format!("Body #{}", body.id));
+                    ui.label(format!("Mass: {:.3}", body.mass));
+                    ui.label(format!("Radius: {:.2}", body.radius));
+                    ui.label(format!("Speed: {:.2}", body.vel.norm()));
+                    ui.label(format!("Position: ({:.1}, {:.1})", body.pos.x, body.pos.y));
+                });
+            }
+
+            // Render the ruler, rubber-banding to the pointer until the
+            // second end is placed
+            if self.measure_mode && !take_screenshot {
+                let ends: Vec<Vector2<f32>> = match self.ruler[..] {
+                    [a] => self
+                        .anchor_pos(a)
+                        .into_iter()
+                        .chain([world_mouse])
+                        .collect(),
+                    [a, b] => self
+                        .anchor_pos(a)
+                        .into_iter()
+                        .chain(self.anchor_pos(b))
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                if let [a, b] = ends[..] {
+                    let stroke = (1.5, Color32::LIGHT_BLUE);
+                    painter.line_segment([to_screen(a), to_screen(b)], stroke);
+                    for end in [a, b] {
+                        painter.circle_stroke(to_screen(end), 3.0, stroke);
+                    }
+                    painter.text(
+                        to_screen((a + b) * 0.5) + Vec2::new(0.0, -6.0),
+                        egui::Align2::CENTER_BOTTOM,
+                        format!("{:.2}", (b - a).norm()),
+                        egui::FontId::proportional(12.0),
+                        Color32::LIGHT_BLUE,
+                    );
+                }
+            }
+
+            // Render the minimap with the visible region outlined
+            if let Some(map) = &minimap
+                && !take_screenshot
+            {
+                let frame = map.rect.expand(4.0);
+                painter.rect_filled(frame, 2.0, Color32::from_black_alpha(200));
+                painter.rect_stroke(
+                    frame,
+                    2.0,
+                    (1.0, Color32::from_gray(90)),
+                    egui::StrokeKind::Inside,
+                );
+                let mut dots = egui::Mesh::default();
+                for body in &self.sim.bodies {
+                    dots.add_colored_rect(
+                        egui::Rect::from_center_size(map.to_screen(body.pos), Vec2::splat(2.0)),
+                        body.color,
+                    );
+                }
+                painter.add(dots);
+                let half_extent = nalgebra_from_vec2(rect.size()) / (2.0 * zoom);
+                let visible = egui::Rect::from_two_pos(
+                    map.to_screen(camera_pos - half_extent),
+                    map.to_screen(camera_pos + half_extent),
+                );
+                painter.with_clip_rect(frame).rect_stroke(
+                    visible,
+                    0.0,
+                    (1.0, Color32::WHITE),
+                    egui::StrokeKind::Middle,
                 );
             }
 
-            if self.show_hud {
+            // Render the scale bar in the bottom-left corner
+            if self.show_scale_bar {
+                let length = render::nice_length(SCALE_BAR_MAX_LEN / zoom);
+                let left = rect.left_bottom() + Vec2::new(SCALE_BAR_MARGIN, -SCALE_BAR_MARGIN);
+                let right = left + Vec2::X * length * zoom;
+                let stroke = (1.5, Color32::WHITE);
+                let tick = Vec2::Y * 4.0;
+                painter.line_segment([left, right], stroke);
+                painter.line_segment([left - tick, left + tick], stroke);
+                painter.line_segment([right - tick, right + tick], stroke);
+                painter.text(
+                    left - tick,
+                    egui::Align2::LEFT_BOTTOM,
+                    // Enough decimals for sub-unit lengths, without float noise
+                    format!(
+                        "{length:.*} units",
+                        (-length.log10()).ceil().max(0.0) as usize
+                    ),
+                    egui::FontId::proportional(12.0),
+                    Color32::WHITE,
+                );
+            }
+
+            // Briefly show the time scale after a hotkey changes it, fading out
+            if let Some(changed_at) = self.time_scale_changed_at
+                && !take_screenshot
+            {
+                let age = input.time - changed_at;
+                if age < TIME_SCALE_NOTICE {
+                    let alpha = (1.0 - age / TIME_SCALE_NOTICE) as f32;
+                    painter.text(
+                        rect.center_top() + Vec2::Y * 24.0,
+                        egui::Align2::CENTER_TOP,
+                        format!("{:.2}×", self.time_scale),
+                        egui::FontId::proportional(24.0),
+                        Color32::WHITE.gamma_multiply(alpha),
+                    );
+                } else {
+                    self.time_scale_changed_at = None;
+                }
+            }
+
+            if self.show_hud && !take_screenshot {
                 egui::Window::new("HUD").show(ctx, |ui| {
-                    ui.label(format!("Bodies: {}", self.bodies.len()));
+                    let frame_time = self.frame_times.back().copied().unwrap_or_default();
+                    let average =
+                        self.frame_times.iter().sum::<f32>() / self.frame_times.len().max(1) as f32;
+                    // No rate for a zero frame time, as before the first frame
+                    let rate = |seconds: f32, decimals: usize| {
+                        if seconds > 0.0 {
+                            format!("{:.*}", decimals, 1.0 / seconds)
+                        } else {
+                            "–".to_owned()
+                        }
+                    };
+                    ui.label(format!(
+                        "FPS: {} ({:.1} ms), average {} ({:.1} ms)",
+                        rate(frame_time, 0),
+                        frame_time * 1e3,
+                        rate(average, 1),
+                        average * 1e3
+                    ));
+                    ui.label(format!("Bodies: {}", self.sim.bodies.len()));
                     ui.label(format!("Zoom: {:.2}", self.zoom));
-                    ui.label(format!("Elastic Collisions: {}", self.elastic));
-                    ui.label(
-                        "Controls:\n\
-                        R: Reset\n\
-                        H: Toggle HUD\n\
-                        E: Toggle Elastic\n\
-                        WASD: Pan\n\
-                        Scroll: Zoom\n\
-                        Click-Drag: Spawn",
+                    ui.label(format!("Kinetic energy: {:.2}", self.energy.kinetic));
+                    ui.label(format!("Potential energy: {:.2}", self.energy.potential));
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Total energy: {:.2}", self.energy.total()));
+                        ui.checkbox(&mut self.show_energy_plot, "Plot");
+                    });
+                    let ratio = self
+                        .energy
+                        .virial_ratio()
+                        .map_or_else(|| "–".to_owned(), |r| format!("{r:.3}"));
+                    ui.label(format!(
+                        "K/|U|: {ratio} (relaxed ≈ 0.5), 2K + U: {:.2}",
+                        self.energy.virial()
+                    ))
+                    .on_hover_text("A bound cluster in equilibrium has 2K + U ≈ 0");
+                    ui.label(format!(
+                        "Energy lost to collisions: {:.2}",
+                        self.sim.collisions.energy_lost
+                    ));
+                    let momentum = self.sim.momentum();
+                    ui.label(format!(
+                        "Momentum: ({:.2}, {:.2}) |p| = {:.2}",
+                        momentum.x,
+                        momentum.y,
+                        momentum.norm()
+                    ));
+                    ui.label(format!(
+                        "Angular momentum (about center of mass): {:.2}",
+                        self.angular_momentum
+                    ));
+                    let collisions = self.sim.collisions;
+                    ui.label(format!(
+                        "Collisions: {} ({} bounces, {} merges), {:.1}/s",
+                        collisions.total(),
+                        collisions.bounces,
+                        collisions.merges,
+                        self.collision_rate
+                    ));
+                    if self.paused && self.paused_steps > 0 {
+                        ui.label(format!("Paused: true (stepped {})", self.paused_steps));
+                    } else {
+                        ui.label(format!("Paused: {}", self.paused));
+                    }
+                    ui.label(format!(
+                        "Step: {:.3} ms{}{}",
+                        self.step_dt * 1e3,
+                        if self.adaptive_dt { " (adaptive)" } else { "" },
+                        if self.substeps > 1 {
+                            format!(" in {} sub-steps", self.substeps)
+                        } else {
+                            String::new()
+                        }
+                    ));
+                    ui.add(
+                        egui::Slider::new(&mut self.time_scale, TIME_SCALE_RANGE)
+                            .logarithmic(true)
+                            .suffix("×")
+                            .text("Time scale"),
+                    );
+                    ui.collapsing("New body", |ui| {
+                        ui.add(
+                            egui::Slider::new(&mut self.selected_size, SIZE_RANGE)
+                                .logarithmic(true)
+                                .text("Size"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut self.selected_density, 0.1..=10.0)
+                                .logarithmic(true)
+                                .text("Density"),
+                        );
+                        ui.horizontal(|ui| {
+                            ui.add_enabled_ui(!self.random_color, |ui| {
+                                ui.color_edit_button_srgba(&mut self.selected_color);
+                            });
+                            ui.checkbox(&mut self.random_color, "Random color");
+                        });
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut self.orbit_mode, "Orbit mode");
+                            if let Some(id) = self.orbit_center {
+                                ui.label(format!("around #{id}"));
+                                if ui.small_button("Clear").clicked() {
+                                    self.orbit_center = None;
+                                }
+                            }
+                        });
+                        egui::ComboBox::from_label("Symmetry")
+                            .selected_text(self.symmetry.label())
+                            .show_ui(ui, |ui| {
+                                for mode in Symmetry::ALL {
+                                    ui.selectable_value(&mut self.symmetry, mode, mode.label());
+                                }
+                            });
+                        ui.add_enabled(
+                            !self.orbit_mode,
+                            egui::Slider::new(&mut self.launch_strength, 0.005..=1.0)
+                                .logarithmic(true)
+                                .text("Launch strength"),
+                        )
+                        .on_hover_text("Launch speed per pixel of drag, in world units per second");
+                        ui.horizontal(|ui| {
+                            ui.label("Seed");
+                            if ui.add(egui::DragValue::new(&mut self.seed)).changed() {
+                                self.rng = StdRng::seed_from_u64(self.seed);
+                            }
+                        });
+                    });
+                    ui.collapsing("Generators", |ui| {
+                        let belt = &mut self.belt_params;
+                        ui.add(egui::Slider::new(&mut belt.count, 10..=5000).text("Asteroids"));
+                        ui.add(
+                            egui::Slider::new(&mut belt.inner_radius, 10.0..=5000.0)
+                                .logarithmic(true)
+                                .text("Inner radius"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut belt.outer_radius, 10.0..=5000.0)
+                                .logarithmic(true)
+                                .text("Outer radius"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut belt.mass, 0.01..=100.0)
+                                .logarithmic(true)
+                                .text("Asteroid mass"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut belt.mass_spread, 0.0..=0.95)
+                                .text("Mass spread"),
+                        );
+                        if ui
+                            .add_enabled(
+                                self.replay.is_none(),
+                                egui::Button::new("Add asteroid belt"),
+                            )
+                            .clicked()
+                        {
+                            self.add_asteroid_belt();
+                        }
+                        ui.separator();
+                        let galaxy = &mut self.galaxy_params;
+                        ui.add(egui::Slider::new(&mut galaxy.count, 10..=20000).text("Stars"));
+                        ui.add(
+                            egui::Slider::new(&mut galaxy.radius, 50.0..=10000.0)
+                                .logarithmic(true)
+                                .text("Disk radius"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut galaxy.central_mass, 1e3..=1e9)
+                                .logarithmic(true)
+                                .text("Central mass"),
+                        );
+                        ui.add(egui::Slider::new(&mut galaxy.arms, 1..=6).text("Spiral arms"));
+                        if ui
+                            .add_enabled(
+                                self.replay.is_none(),
+                                egui::Button::new("Add spiral galaxy"),
+                            )
+                            .clicked()
+                        {
+                            let galaxy = generators::spiral_galaxy(
+                                self.view_center(),
+                                self.sim.g,
+                                &self.galaxy_params,
+                                &mut self.rng,
+                            );
+                            self.add_generated(galaxy);
+                        }
+                        ui.separator();
+                        let cluster = &mut self.cluster_params;
+                        ui.add(
+                            egui::Slider::new(&mut cluster.count, 10..=5000).text("Cluster bodies"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut cluster.position_sigma, 1.0..=2000.0)
+                                .logarithmic(true)
+                                .text("Position σ"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut cluster.velocity_sigma, 0.0..=100.0)
+                                .text("Velocity σ"),
+                        );
+                        ui.label(format!(
+                            "Press {} to spawn a cluster at the cursor",
+                            self.bindings.key(Command::SpawnCluster).name()
+                        ));
+                    });
+                    egui::ComboBox::from_label("Collisions")
+                        .selected_text(self.sim.collision_mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in CollisionMode::ALL {
+                                ui.selectable_value(
+                                    &mut self.sim.collision_mode,
+                                    mode,
+                                    mode.label(),
+                                );
+                            }
+                        });
+                    ui.add_enabled(
+                        self.sim.collision_mode == CollisionMode::Bounce
+                            || self.sim.boundary == Boundary::Walls,
+                        egui::Slider::new(&mut self.sim.restitution, 0.0..=1.0).text("Restitution"),
+                    );
+                    egui::ComboBox::from_label("Boundary")
+                        .selected_text(self.sim.boundary.label())
+                        .show_ui(ui, |ui| {
+                            for boundary in Boundary::ALL {
+                                ui.selectable_value(
+                                    &mut self.sim.boundary,
+                                    boundary,
+                                    boundary.label(),
+                                );
+                            }
+                        });
+                    ui.add_enabled_ui(self.sim.boundary != Boundary::Open, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("World size");
+                            for extent in self.sim.world_size.iter_mut() {
+                                ui.add(egui::DragValue::new(extent).speed(10.0).range(10.0..=1e6));
+                            }
+                        });
+                    });
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.sim.cull_escapees, "Cull beyond");
+                        ui.add_enabled(
+                            self.sim.cull_escapees,
+                            egui::Slider::new(&mut self.sim.cull_distance, 100.0..=1e6)
+                                .logarithmic(true),
+                        );
+                    });
+                    if self.sim.culled > 0 {
+                        ui.label(format!("Culled: {}", self.sim.culled));
+                    }
+                    egui::ComboBox::from_label("Integrator")
+                        .selected_text(self.sim.integrator.label())
+                        .show_ui(ui, |ui| {
+                            for integrator in Integrator::ALL {
+                                ui.selectable_value(
+                                    &mut self.sim.integrator,
+                                    integrator,
+                                    integrator.label(),
+                                );
+                            }
+                        });
+                    ui.add(egui::Slider::new(&mut self.substeps, 1..=16).text("Sub-steps"))
+                        .on_hover_text(
+                            "Split each step into this many smaller ones, so fast bodies are \
+                             less likely to pass through each other. Costs this many times as \
+                             much per step.",
+                        );
+                    ui.checkbox(&mut self.adaptive_dt, "Adaptive timestep")
+                        .on_hover_text(
+                            "Shrink the step during close, fast encounters. Checks every pair of \
+                             bodies each step.",
+                        );
+                    ui.add_enabled_ui(self.adaptive_dt, |ui| {
+                        ui.add(
+                            egui::Slider::new(&mut self.min_dt, ADAPTIVE_DT_RANGE)
+                                .logarithmic(true)
+                                .suffix(" s")
+                                .text("Min step"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut self.max_dt, ADAPTIVE_DT_RANGE)
+                                .logarithmic(true)
+                                .suffix(" s")
+                                .text("Max step"),
+                        );
+                    });
+                    ui.add(
+                        egui::Slider::new(&mut self.sim.g, 1e-4..=10.0)
+                            .logarithmic(true)
+                            .text("Gravity G"),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut self.sim.softening, 0.0..=50.0).text("Softening ε"),
+                    );
+                    egui::ComboBox::from_label("Gravity")
+                        .selected_text(self.sim.solver.label())
+                        .show_ui(ui, |ui| {
+                            for solver in GravitySolver::ALL {
+                                ui.selectable_value(&mut self.sim.solver, solver, solver.label());
+                            }
+                        });
+                    ui.add_enabled(
+                        self.sim.solver == GravitySolver::BarnesHut,
+                        egui::Slider::new(&mut self.sim.theta, 0.0..=1.0).text("Theta θ"),
+                    );
+                    egui::ComboBox::from_label("Labels")
+                        .selected_text(self.body_label.label())
+                        .show_ui(ui, |ui| {
+                            for mode in BodyLabel::ALL {
+                                ui.selectable_value(&mut self.body_label, mode, mode.label());
+                            }
+                        });
+                    egui::ComboBox::from_label("Color")
+                        .selected_text(self.color_mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in ColorMode::ALL {
+                                ui.selectable_value(&mut self.color_mode, mode, mode.label());
+                            }
+                        });
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.show_grid, "Grid");
+                        ui.checkbox(&mut self.show_potential, "Potential");
+                        ui.checkbox(&mut self.show_field, "Field");
+                        ui.checkbox(&mut self.show_starfield, "Stars");
+                        ui.checkbox(&mut self.show_scale_bar, "Scale bar");
+                        ui.checkbox(&mut self.show_minimap, "Minimap");
+                        ui.checkbox(&mut self.show_merge_bursts, "Merge bursts");
+                    });
+                    ui.checkbox(&mut self.mesh_bodies, "Batch bodies into one mesh")
+                        .on_hover_text("Off draws each body as its own circle shape");
+                    ui.checkbox(&mut self.show_trails, "Trails");
+                    ui.add_enabled(
+                        self.show_trails,
+                        egui::Slider::new(&mut self.trail_length, 10..=1000).text("Trail length"),
+                    );
+                    ui.checkbox(&mut self.show_glow, "Glow");
+                    ui.add_enabled(
+                        self.show_glow,
+                        egui::Slider::new(&mut self.glow_mass, 1.0..=1e9)
+                            .logarithmic(true)
+                            .text("Glow above mass"),
+                    );
+                    ui.checkbox(&mut self.show_comet_tails, "Comet tails");
+                    ui.add_enabled(
+                        self.show_comet_tails,
+                        egui::Slider::new(&mut self.comet_speed, 1.0..=1000.0)
+                            .logarithmic(true)
+                            .text("Tail above speed"),
+                    );
+                    ui.checkbox(&mut self.show_velocity, "Velocity vectors");
+                    ui.add_enabled(
+                        self.show_velocity,
+                        egui::Slider::new(&mut self.velocity_arrow_scale, 0.1..=50.0)
+                            .logarithmic(true)
+                            .text("Velocity scale"),
                     );
+                    ui.checkbox(&mut self.show_acceleration, "Acceleration vectors");
+                    ui.add_enabled(
+                        self.show_acceleration,
+                        egui::Slider::new(&mut self.acceleration_arrow_scale, 1.0..=10000.0)
+                            .logarithmic(true)
+                            .text("Acceleration scale"),
+                    );
+                    ui.checkbox(&mut self.show_tidal, "Tidal axes");
+                    ui.add_enabled(
+                        self.show_tidal,
+                        egui::Slider::new(&mut self.tidal_scale, 1.0..=100_000.0)
+                            .logarithmic(true)
+                            .text("Tidal scale"),
+                    );
+                    ui.horizontal(|ui| {
+                        let mut barycentric = self.barycentric_view;
+                        if ui.checkbox(&mut barycentric, "Barycentric view").changed() {
+                            self.set_barycentric_view(barycentric);
+                        }
+                        ui.add_enabled(
+                            self.barycentric_view,
+                            egui::Checkbox::new(
+                                &mut self.barycentric_velocities,
+                                "Relative velocities",
+                            ),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(
+                                self.selected_body.is_some(),
+                                egui::Checkbox::new(&mut self.follow_selected, "Follow selected"),
+                            )
+                            .changed()
+                        {
+                            self.lock_to_com &= !self.follow_selected;
+                        }
+                        if ui.checkbox(&mut self.lock_to_com, "Lock to COM").changed() {
+                            self.follow_selected &= !self.lock_to_com;
+                        }
+                        if ui
+                            .add_enabled(
+                                self.selected_body.is_some() || !self.multi_selection.is_empty(),
+                                egui::Button::new("Delete selected"),
+                            )
+                            .clicked()
+                        {
+                            self.delete_selected();
+                        }
+                    });
+                    egui::ComboBox::from_label("On drop")
+                        .selected_text(self.drop_velocity.label())
+                        .show_ui(ui, |ui| {
+                            for mode in DropVelocity::ALL {
+                                ui.selectable_value(&mut self.drop_velocity, mode, mode.label());
+                            }
+                        });
+                    if !self.multi_selection.is_empty() {
+                        ui.label(format!(
+                            "Box selection: {} bodies",
+                            self.multi_selection.len()
+                        ));
+                    }
+                    ui.add_enabled(
+                        self.selected_body.is_some(),
+                        egui::Checkbox::new(&mut self.show_roche_limit, "Roche limit"),
+                    );
+                    if self.show_roche_limit
+                        && let Some(index) = self.selected_body.and_then(|id| self.sim.find(id))
+                    {
+                        match self.sim.roche_limit_of(index) {
+                            Some((primary, limit)) => {
+                                let primary = &self.sim.bodies[primary];
+                                let distance = (primary.pos - self.sim.bodies[index].pos).norm();
+                                ui.label(format!(
+                                    "Roche limit around #{}: {limit:.1} (now at {distance:.1})",
+                                    primary.id
+                                ));
+                            }
+                            None => {
+                                ui.label("Roche limit: no heavier body nearby");
+                            }
+                        }
+                    }
+                    if let (Some(a), Some(b)) = (self.selected_body, self.second_selected) {
+                        ui.label(format!("Bodies #{a} and #{b}:"));
+                        self.pair_readout(ui, a, b);
+                    }
+                    if ui.checkbox(&mut self.measure_mode, "Measure").changed() {
+                        self.ruler.clear();
+                    }
+                    if self.measure_mode {
+                        self.ruler_readout(ui);
+                    }
+                    ui.checkbox(&mut self.show_center_of_mass, "Center of mass");
+                    if self.show_center_of_mass
+                        && let Some(com) = self.sim.center_of_mass()
+                    {
+                        ui.label(format!("Center of mass: ({:.1}, {:.1})", com.x, com.y));
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Save").clicked() {
+                            self.save_scene_dialog();
+                        }
+                        if ui.button("Load").clicked() {
+                            self.load_scene_dialog();
+                        }
+                        if ui.button("Fit view").clicked() {
+                            self.fit_view();
+                        }
+                        if ui
+                            .button("Import CSV")
+                            .on_hover_text("Rows of x,y,vx,vy,mass,radius[,#rrggbb]")
+                            .clicked()
+                        {
+                            self.import_csv_dialog();
+                        }
+                        ui.checkbox(&mut self.csv_import_append, "Append");
+                        if ui.button("Screenshot").clicked() {
+                            self.screenshot_requested = true;
+                        }
+                        ui.menu_button("Presets", |ui| {
+                            for preset in Preset::ALL {
+                                if ui.button(preset.label()).clicked() {
+                                    self.load_preset(preset);
+                                    ui.close_menu();
+                                }
+                            }
+                            ui.separator();
+                            ui.add(
+                                egui::Slider::new(
+                                    &mut self.preset_params.binary_mass_ratio,
+                                    0.05..=1.0,
+                                )
+                                .text("Binary mass ratio"),
+                            );
+                        });
+                    });
+                    ui.horizontal(|ui| {
+                        if self.is_recording {
+                            if ui.button("Stop recording").clicked() {
+                                self.stop_recording();
+                            }
+                        } else if ui
+                            .add_enabled(self.replay.is_none(), egui::Button::new("Record"))
+                            .clicked()
+                        {
+                            self.start_recording();
+                        }
+                        let can_replay = !self.recording.is_empty() && self.replay.is_none();
+                        if ui
+                            .add_enabled(can_replay, egui::Button::new("Replay"))
+                            .clicked()
+                        {
+                            self.start_replay();
+                        }
+                        if !self.recording.is_empty() {
+                            ui.label(format!(
+                                "{} steps, {:.1} MB",
+                                self.recording.len(),
+                                self.recording.byte_size() as f64 / (1024.0 * 1024.0)
+                            ));
+                        }
+                    });
+                    ui.add_enabled_ui(!self.is_recording, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut self.export_csv, "Export CSV to");
+                            ui.add_enabled(
+                                self.export_csv,
+                                egui::TextEdit::singleline(&mut self.csv_path),
+                            );
+                        });
+                    });
+                    if let Some(message) = &self.status_message {
+                        ui.label(message);
+                    }
+                    ui.collapsing("Key bindings", |ui| self.key_bindings_ui(ui));
+                    ui.label(self.controls_help());
                 });
             }
 
-            ctx.request_repaint();
+            if take_screenshot {
+                // Overlays are left out of this frame; the capture is cropped
+                // to the view, so the side and bottom panels are too
+                ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot(egui::UserData::new(rect)));
+            }
+
+            // Keep redrawing only while something moves or the user is in the
+            // middle of a gesture; otherwise egui idles until the next input
+            let running = match &self.replay {
+                Some(replay) => replay.playing,
+                None => !self.paused,
+            };
+            let animating =
+                running || !self.particles.is_empty() || self.time_scale_changed_at.is_some();
+            let interacting = input.pointer.any_down()
+                || !input.keys_down.is_empty()
+                || self.selected_pos.is_some()
+                || self.dragging_body.is_some()
+                || self.box_select.is_some();
+            if animating || interacting || take_screenshot {
+                ctx.request_repaint();
+            } else if self.selected_body.is_some() {
+                // Just enough frames for the selection ring to pulse smoothly
+                ctx.request_repaint_after(SELECTION_PULSE_FRAME);
+            }
         });
     }
 }
 
 fn main() -> eframe::Result<()> {
+    let args = match cli::Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{message}\n\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    let app = match initial_app(&args) {
+        Ok(app) => app,
+        Err(err) => {
+            let path = args.scene.as_deref().unwrap_or(Path::new(""));
+            eprintln!("failed to load {}: {err}", path.display());
+            std::process::exit(1);
+        }
+    };
+    if args.headless {
+        headless::run(app, args.steps);
+        return Ok(());
+    }
+
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_inner_size([1280.0, 720.0]),
+        viewport: egui::ViewportBuilder::default().with_inner_size(args.window_size),
         ..Default::default()
     };
     eframe::run_native(
         "Gravisim (Egui)",
         options,
-        Box::new(|_cc| Ok(Box::new(GravisimApp::default()))),
+        Box::new(|_cc| Ok(Box::new(app))),
     )
 }
+
+/// Builds the starting state from the command line: the scene file if any,
+/// then the G override, then the random bodies.
+fn initial_app(args: &cli::Args) -> std::io::Result<GravisimApp> {
+    // The real viewport is only known after the first frame; assume the
+    // requested window size so fitting the view works before that.
+    let mut app = GravisimApp {
+        viewport: egui::Rect::from_min_size(Pos2::ZERO, Vec2::from(args.window_size)),
+        ..Default::default()
+    };
+    match KeyBindings::load(Path::new(KEYBINDINGS_PATH)) {
+        Ok(bindings) => app.bindings = bindings,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => app.status_message = Some(format!("Key bindings not loaded: {err}")),
+    }
+    if let Some(path) = &args.scene {
+        app.load_scene(path)?;
+    }
+    if let Some(g) = args.g {
+        app.sim.g = g;
+    }
+    if args.bodies > 0 {
+        app.spawn_random_bodies(args.bodies);
+        if args.scene.is_none() {
+            app.fit_view();
+        }
+    }
+    Ok(app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn large_scrolls_keep_zoom_in_range() {
+        for scroll in [1e4, -1e4] {
+            let mut zoom = 1.0;
+            for _ in 0..100 {
+                zoom = scrolled_zoom(zoom, scroll);
+                assert!(!zoom.is_nan(), "NaN zoom after scroll {scroll}");
+                assert!(
+                    ZOOM_RANGE.contains(&zoom),
+                    "zoom {zoom} after scroll {scroll}"
+                );
+            }
+        }
+    }
+}
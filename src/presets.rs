@@ -0,0 +1,188 @@
+//! Ready-made starting configurations.
+
+use std::f32::consts::TAU;
+
+use eframe::egui::Color32;
+use nalgebra::Vector2;
+
+use crate::sim::{Body, circular_velocity};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    SolarSystem,
+    BinaryStar,
+    FigureEight,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::SolarSystem, Preset::BinaryStar, Preset::FigureEight];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Preset::SolarSystem => "Solar system",
+            Preset::BinaryStar => "Binary star",
+            Preset::FigureEight => "Three-body figure eight",
+        }
+    }
+
+    /// Builds the preset's bodies for gravitational constant `g`.
+    pub fn bodies(self, g: f32, params: &PresetParams) -> Vec<Body> {
+        match self {
+            Preset::SolarSystem => solar_system(g),
+            Preset::BinaryStar => binary_star(g, params.binary_mass_ratio),
+            Preset::FigureEight => figure_eight(g),
+        }
+    }
+}
+
+/// User-tweakable knobs for the presets that have them.
+#[derive(Clone, Debug)]
+pub struct PresetParams {
+    /// Mass of the lighter star over the heavier one, in `(0, 1]`.
+    pub binary_mass_ratio: f32,
+}
+
+impl Default for PresetParams {
+    fn default() -> Self {
+        Self {
+            binary_mass_ratio: 0.5,
+        }
+    }
+}
+
+/// Shifts positions and velocities so the center of mass sits at rest at the
+/// origin.
+fn recenter(bodies: &mut [Body]) {
+    let mass: f32 = bodies.iter().map(|b| b.mass).sum();
+    if mass <= 0.0 {
+        return;
+    }
+    let com: Vector2<f32> = bodies.iter().map(|b| b.pos * b.mass).sum::<Vector2<f32>>() / mass;
+    let com_vel: Vector2<f32> = bodies.iter().map(|b| b.vel * b.mass).sum::<Vector2<f32>>() / mass;
+    for body in bodies {
+        body.pos -= com;
+        body.vel -= com_vel;
+    }
+}
+
+/// World units per astronomical unit in the solar-system preset.
+const AU: f32 = 120.0;
+/// Orbital period of the preset's Earth, in simulated seconds. The sun's mass
+/// is derived from this (and `g`) rather than from real masses, so the inner
+/// planets complete an orbit in well under a minute.
+const EARTH_PERIOD: f32 = 30.0;
+
+/// The sun and the planets out to Saturn, at their real relative distances
+/// (scaled by [`AU`]) on circular orbits. Planet masses are far below the sun's
+/// so their mutual perturbations stay small, as in the real system.
+fn solar_system(g: f32) -> Vec<Body> {
+    // Kepler's third law: GM = 4π² a³ / T².
+    let sun_mass = TAU * TAU * AU.powi(3) / (EARTH_PERIOD * EARTH_PERIOD * g);
+    let sun_radius = 30.0;
+    let sun = Body::new(
+        Vector2::zeros(),
+        Vector2::zeros(),
+        sun_mass / (sun_radius * sun_radius),
+        sun_radius,
+        Color32::from_rgb(255, 210, 80),
+    );
+
+    // (distance in AU, radius, color)
+    let planets = [
+        (0.39, 3.0, Color32::from_rgb(170, 160, 150)),
+        (0.72, 5.0, Color32::from_rgb(230, 200, 140)),
+        (1.00, 5.0, Color32::from_rgb(80, 140, 255)),
+        (1.52, 4.0, Color32::from_rgb(220, 100, 60)),
+        (5.20, 12.0, Color32::from_rgb(210, 170, 130)),
+        (9.54, 10.0, Color32::from_rgb(230, 210, 150)),
+    ];
+    let mut bodies = vec![sun];
+    for (i, (distance, radius, color)) in planets.into_iter().enumerate() {
+        let angle = i as f32 * 2.4;
+        let offset = Vector2::new(angle.cos(), angle.sin()) * distance * AU;
+        let vel = circular_velocity(g, sun_mass, offset);
+        bodies.push(Body::new(offset, vel, 1.0, radius, color));
+    }
+    recenter(&mut bodies);
+    bodies
+}
+
+/// Separation of the two stars in the binary preset, in world units.
+const BINARY_SEPARATION: f32 = 300.0;
+/// Orbital period of the binary preset, in simulated seconds.
+const BINARY_PERIOD: f32 = 20.0;
+
+/// Two stars on circular orbits about their common barycenter, which stays
+/// at rest at the origin, plus a few light planets on wide circumbinary
+/// orbits. `mass_ratio` is the lighter star's mass over the heavier one's.
+fn binary_star(g: f32, mass_ratio: f32) -> Vec<Body> {
+    let q = mass_ratio.clamp(0.01, 1.0);
+    let a = BINARY_SEPARATION;
+    let total_mass = TAU * TAU * a.powi(3) / (BINARY_PERIOD * BINARY_PERIOD * g);
+    let m1 = total_mass / (1.0 + q);
+    let m2 = total_mass - m1;
+
+    // Each star sits at distance a·m_other/M from the barycenter and moves
+    // with the same fraction of the relative orbital speed.
+    let relative_speed = (g * total_mass / a).sqrt();
+    let star = |mass: f32, side: f32, radius: f32, color: Color32| {
+        let share = (total_mass - mass) / total_mass;
+        let pos = Vector2::new(side * a * share, 0.0);
+        let vel = Vector2::new(0.0, side * relative_speed * share);
+        Body::new(pos, vel, mass / (radius * radius), radius, color)
+    };
+    let mut bodies = vec![
+        star(m1, -1.0, 25.0, Color32::from_rgb(255, 200, 120)),
+        star(m2, 1.0, 25.0 * q.cbrt(), Color32::from_rgb(150, 190, 255)),
+    ];
+
+    for (i, distance) in [3.0, 4.0, 5.5].into_iter().enumerate() {
+        let angle = i as f32 * 2.1;
+        let offset = Vector2::new(angle.cos(), angle.sin()) * distance * a;
+        let vel = circular_velocity(g, total_mass, offset);
+        bodies.push(Body::new(offset, vel, 1.0, 5.0, Body::DEFAULT_COLOR));
+    }
+    recenter(&mut bodies);
+    bodies
+}
+
+/// World units per unit length of the figure-eight solution.
+const FIGURE_EIGHT_SCALE: f32 = 200.0;
+/// Simulated seconds per unit time of the figure-eight solution, chosen so
+/// a full period (≈ 6.326 units) takes about 15 seconds.
+const FIGURE_EIGHT_TIME: f32 = 2.37;
+
+/// The periodic figure-eight solution of the equal-mass three-body problem
+/// (Chenciner & Montgomery), with the initial conditions from Simó's
+/// numerical solution in units where `G = m = 1`. Rescaled for `g`, it is a
+/// sensitive integrator test: accumulated errors make the bodies drift off
+/// the periodic orbit and eventually break it up.
+fn figure_eight(g: f32) -> Vec<Body> {
+    let x1 = Vector2::new(-0.970_004_4, 0.243_087_53);
+    let v3 = Vector2::new(-0.932_407_4, -0.864_731_5);
+    let states = [(x1, -v3 / 2.0), (-x1, -v3 / 2.0), (Vector2::zeros(), v3)];
+
+    let length = FIGURE_EIGHT_SCALE;
+    let time = FIGURE_EIGHT_TIME;
+    // With G = m = 1 in the solution's units, G m = L³ / T² in ours.
+    let mass = length.powi(3) / (time * time * g);
+    let radius = 10.0;
+    let colors = [
+        Color32::from_rgb(255, 120, 120),
+        Color32::from_rgb(120, 255, 140),
+        Color32::from_rgb(120, 160, 255),
+    ];
+    states
+        .into_iter()
+        .zip(colors)
+        .map(|((pos, vel), color)| {
+            Body::new(
+                pos * length,
+                vel * (length / time),
+                mass / (radius * radius),
+                radius,
+                color,
+            )
+        })
+        .collect()
+}
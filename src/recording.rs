@@ -0,0 +1,159 @@
+//! Step-by-step capture of the simulation for later playback.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::mem::size_of;
+use std::path::Path;
+
+use eframe::egui::Color32;
+use nalgebra::Vector2;
+
+use crate::sim::Body;
+
+/// Recordings stop growing past this many bytes of body state.
+pub const MAX_RECORDING_BYTES: usize = 512 * 1024 * 1024;
+
+/// The part of a body's state needed to draw it, without its trail.
+#[derive(Clone, Copy)]
+struct BodyState {
+    id: u64,
+    pos: Vector2<f32>,
+    vel: Vector2<f32>,
+    acc: Vector2<f32>,
+    mass: f32,
+    radius: f32,
+    color: Color32,
+    fixed: bool,
+    temp: f32,
+}
+
+impl BodyState {
+    fn new(body: &Body) -> Self {
+        Self {
+            id: body.id,
+            pos: body.pos,
+            vel: body.vel,
+            acc: body.acc,
+            mass: body.mass,
+            radius: body.radius,
+            color: body.color,
+            fixed: body.fixed,
+            temp: body.temp,
+        }
+    }
+
+    fn to_body(self) -> Body {
+        Body {
+            id: self.id,
+            pos: self.pos,
+            vel: self.vel,
+            acc: self.acc,
+            mass: self.mass,
+            radius: self.radius,
+            color: self.color,
+            trail: Default::default(),
+            fixed: self.fixed,
+            temp: self.temp,
+        }
+    }
+}
+
+/// Body states captured once per physics step, oldest first.
+#[derive(Default)]
+pub struct Recording {
+    frames: Vec<Vec<BodyState>>,
+    bytes: usize,
+}
+
+impl Recording {
+    /// Appends a frame holding `bodies`. Returns `false`, leaving the
+    /// recording unchanged, once it has reached [`MAX_RECORDING_BYTES`].
+    pub fn capture(&mut self, bodies: &[Body]) -> bool {
+        let bytes = size_of::<Vec<BodyState>>() + bodies.len() * size_of::<BodyState>();
+        if self.bytes + bytes > MAX_RECORDING_BYTES {
+            return false;
+        }
+        self.frames
+            .push(bodies.iter().map(BodyState::new).collect());
+        self.bytes += bytes;
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.bytes = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Approximate memory held by the recorded frames.
+    pub fn byte_size(&self) -> usize {
+        self.bytes
+    }
+
+    /// Rebuilds the bodies of frame `index`, without trails.
+    pub fn bodies(&self, index: usize) -> Vec<Body> {
+        self.frames[index]
+            .iter()
+            .map(|state| state.to_body())
+            .collect()
+    }
+}
+
+/// Playback position in a [`Recording`], replacing the live simulation
+/// while it lasts.
+pub struct Replay {
+    pub frame: usize,
+    pub playing: bool,
+    /// Simulated time owed to playback since the last frame advanced.
+    pub clock: f32,
+    /// The live bodies, restored when the replay ends.
+    pub live_bodies: Vec<Body>,
+}
+
+/// Steps between flushes of a CSV export, so a crash loses at most about a
+/// second of simulated time.
+const CSV_FLUSH_INTERVAL: u64 = 120;
+
+/// Trajectories streamed to a CSV file as `step,body_id,x,y,vx,vy,mass`
+/// rows, one per body per step.
+pub struct CsvExport {
+    writer: BufWriter<File>,
+    step: u64,
+}
+
+impl CsvExport {
+    /// Creates (or truncates) the file at `path` and writes the header.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "step,body_id,x,y,vx,vy,mass")?;
+        Ok(Self { writer, step: 0 })
+    }
+
+    /// Writes one row per body for the next step.
+    pub fn write_step(&mut self, bodies: &[Body]) -> io::Result<()> {
+        for body in bodies {
+            writeln!(
+                self.writer,
+                "{},{},{},{},{},{},{}",
+                self.step, body.id, body.pos.x, body.pos.y, body.vel.x, body.vel.y, body.mass
+            )?;
+        }
+        self.step += 1;
+        if self.step.is_multiple_of(CSV_FLUSH_INTERVAL) {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Flushes the remaining rows, reporting any error dropping would hide.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,301 @@
+use eframe::egui::{Color32, Mesh, Pos2, Rect, Vec2};
+use nalgebra::Vector2;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::sim::Body;
+
+/// How bodies are colored when drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Each body's own color.
+    #[default]
+    Fixed,
+    /// Blue (slow) to red (fast), scaled to the current frame's speed range.
+    Speed,
+    /// Blue (light) to red (heavy) on a log scale, since merges can make
+    /// masses span several orders of magnitude.
+    Mass,
+    /// Each body's own color glowing red, then white, with collision heat,
+    /// scaled to the hottest body in the frame.
+    Temperature,
+}
+
+impl ColorMode {
+    pub const ALL: [ColorMode; 4] = [
+        ColorMode::Fixed,
+        ColorMode::Speed,
+        ColorMode::Mass,
+        ColorMode::Temperature,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ColorMode::Fixed => "Fixed",
+            ColorMode::Speed => "Speed",
+            ColorMode::Mass => "Mass",
+            ColorMode::Temperature => "Temperature",
+        }
+    }
+
+    /// The quantity mapped onto the color ramp, or `None` in fixed mode.
+    fn value(self, body: &Body) -> Option<f32> {
+        match self {
+            ColorMode::Fixed => None,
+            ColorMode::Speed => Some(body.vel.norm()),
+            ColorMode::Mass => Some(body.mass.max(f32::MIN_POSITIVE).ln()),
+            ColorMode::Temperature => Some(body.temp),
+        }
+    }
+}
+
+/// Colors for one frame, with the ramp auto-scaled to the range of values
+/// present in the scene.
+pub struct BodyColors {
+    mode: ColorMode,
+    min: f32,
+    max: f32,
+}
+
+impl BodyColors {
+    pub fn new(mode: ColorMode, bodies: &[Body]) -> Self {
+        let (min, max) = bodies
+            .iter()
+            .filter_map(|b| mode.value(b))
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), v| {
+                (lo.min(v), hi.max(v))
+            });
+        Self { mode, min, max }
+    }
+
+    pub fn color(&self, body: &Body) -> Color32 {
+        match self.mode.value(body) {
+            None => body.color,
+            Some(v) if self.mode == ColorMode::Temperature => {
+                // Scaled from zero rather than the coolest body, so a frame
+                // of equally warm bodies doesn't all glow white.
+                let t = if self.max > 0.0 { v / self.max } else { 0.0 };
+                glow(body.color, t)
+            }
+            Some(v) => {
+                let span = self.max - self.min;
+                let t = if span > 0.0 {
+                    (v - self.min) / span
+                } else {
+                    0.5
+                };
+                ramp(t)
+            }
+        }
+    }
+}
+
+/// Blue-to-red color ramp for `t` in `[0, 1]`.
+fn ramp(t: f32) -> Color32 {
+    let cold = Color32::from_rgb(40, 80, 255);
+    let hot = Color32::from_rgb(255, 50, 30);
+    cold.lerp_to_gamma(hot, t.clamp(0.0, 1.0))
+}
+
+/// `base` heated by `t` in `[0, 1]`: blended toward red, then white.
+fn glow(base: Color32, t: f32) -> Color32 {
+    let red = Color32::from_rgb(255, 60, 20);
+    let t = t.clamp(0.0, 1.0);
+    if t < 0.5 {
+        base.lerp_to_gamma(red, t * 2.0)
+    } else {
+        red.lerp_to_gamma(Color32::WHITE, t * 2.0 - 1.0)
+    }
+}
+
+/// World-space spacing of the background grid at `zoom`: the smallest power
+/// of ten whose lines are at least `min_pixels` apart on screen.
+pub fn grid_spacing(zoom: f32, min_pixels: f32) -> f32 {
+    10f32.powf((min_pixels / zoom).log10().ceil())
+}
+
+/// The largest "nice" length (1, 2 or 5 times a power of ten) no longer than
+/// `max_len`.
+pub fn nice_length(max_len: f32) -> f32 {
+    let magnitude = 10f32.powf(max_len.log10().floor());
+    [5.0, 2.0, 1.0]
+        .into_iter()
+        .map(|step| step * magnitude)
+        .find(|&len| len <= max_len)
+        .unwrap_or(magnitude)
+}
+
+/// Width of the transparent rim around a meshed disk, in pixels, which
+/// stands in for the anti-aliasing `Painter::circle_filled` would do.
+const DISK_FEATHER: f32 = 1.0;
+
+/// Appends a filled disk to `mesh` as a triangle fan with a feathered rim,
+/// so many bodies can be drawn in one batch instead of one shape each.
+pub fn add_disk(mesh: &mut Mesh, center: Pos2, radius: f32, color: Color32) {
+    // Roughly one segment per two pixels of circumference.
+    let segments = ((radius * std::f32::consts::PI).ceil() as u32).clamp(8, 64);
+    let inner = (radius - DISK_FEATHER * 0.5).max(0.0);
+    let outer = radius + DISK_FEATHER * 0.5;
+    let first = mesh.vertices.len() as u32;
+    mesh.colored_vertex(center, color);
+    for k in 0..segments {
+        let angle = k as f32 / segments as f32 * std::f32::consts::TAU;
+        let dir = Vec2::angled(angle);
+        mesh.colored_vertex(center + dir * inner, color);
+        mesh.colored_vertex(center + dir * outer, Color32::TRANSPARENT);
+    }
+    for k in 0..segments {
+        let (a, b) = (2 * k, 2 * ((k + 1) % segments));
+        let (in_a, out_a) = (first + 1 + a, first + 2 + a);
+        let (in_b, out_b) = (first + 1 + b, first + 2 + b);
+        mesh.add_triangle(first, in_a, in_b);
+        mesh.add_triangle(in_a, out_a, out_b);
+        mesh.add_triangle(in_a, out_b, in_b);
+    }
+}
+
+/// Side of the square screen-space tile the starfield repeats over, in
+/// pixels.
+const STAR_TILE: f32 = 512.0;
+const STARS_PER_TILE: usize = 120;
+
+/// A fixed, tiling pattern of background stars.
+pub struct Starfield {
+    /// Position within the tile, size and color of each star.
+    stars: Vec<(Vec2, f32, Color32)>,
+}
+
+impl Starfield {
+    /// Generates the pattern; the same seed always gives the same stars.
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let stars = (0..STARS_PER_TILE)
+            .map(|_| {
+                let pos = Vec2::new(rng.random(), rng.random()) * STAR_TILE;
+                // Mostly faint, small stars with the occasional bright one.
+                let brightness = rng.random::<f32>().powi(3);
+                let size = 1.0 + brightness;
+                let gray = (60.0 + 195.0 * brightness) as u8;
+                (pos, size, Color32::from_gray(gray))
+            })
+            .collect();
+        Self { stars }
+    }
+
+    /// Draws the stars tiled over `rect`, shifted by `offset` pixels so the
+    /// field can scroll more slowly than the scene for a sense of depth.
+    pub fn mesh(&self, rect: Rect, offset: Vec2) -> Mesh {
+        let mut mesh = Mesh::default();
+        let shift = Vec2::new(
+            offset.x.rem_euclid(STAR_TILE),
+            offset.y.rem_euclid(STAR_TILE),
+        );
+        let origin = rect.min - shift;
+        let tiles_x = (rect.width() / STAR_TILE).ceil() as usize + 1;
+        let tiles_y = (rect.height() / STAR_TILE).ceil() as usize + 1;
+        for tile_y in 0..tiles_y {
+            for tile_x in 0..tiles_x {
+                let tile = origin + Vec2::new(tile_x as f32, tile_y as f32) * STAR_TILE;
+                for &(pos, size, color) in &self.stars {
+                    let center: Pos2 = tile + pos;
+                    if rect.contains(center) {
+                        mesh.add_colored_rect(
+                            Rect::from_center_size(center, Vec2::splat(size)),
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+        mesh
+    }
+}
+
+/// Maps a world-space box onto a small screen rect, preserving the aspect
+/// ratio.
+pub struct Minimap {
+    pub rect: Rect,
+    center: Vector2<f32>,
+    scale: f32,
+}
+
+impl Minimap {
+    /// Fits the world box `min`..`max` inside `rect`.
+    pub fn new(rect: Rect, min: Vector2<f32>, max: Vector2<f32>) -> Self {
+        let extent = (max - min).max().max(f32::EPSILON);
+        Self {
+            rect,
+            center: (min + max) * 0.5,
+            scale: rect.width().min(rect.height()) / extent,
+        }
+    }
+
+    pub fn to_screen(&self, world: Vector2<f32>) -> Pos2 {
+        let offset = (world - self.center) * self.scale;
+        self.rect.center() + Vec2::new(offset.x, offset.y)
+    }
+
+    pub fn to_world(&self, screen: Pos2) -> Vector2<f32> {
+        let offset = screen - self.rect.center();
+        self.center + Vector2::new(offset.x, offset.y) / self.scale
+    }
+}
+
+/// Number of discrete color bands in the potential heatmap.
+const POTENTIAL_BANDS: f32 = 12.0;
+
+/// The gravitational potential sampled at the centers of square screen
+/// cells, drawn as banded colors so equipotential contours stand out.
+pub struct PotentialMap {
+    rect: Rect,
+    cell: f32,
+    cols: usize,
+    values: Vec<f32>,
+}
+
+impl PotentialMap {
+    /// Samples the potential over `rect` in cells of `cell` pixels.
+    /// `potentials` gets every cell center at once, so it can batch them.
+    pub fn sample(rect: Rect, cell: f32, potentials: impl FnOnce(&[Pos2]) -> Vec<f32>) -> Self {
+        let cols = (rect.width() / cell).ceil() as usize;
+        let rows = (rect.height() / cell).ceil() as usize;
+        let centers: Vec<Pos2> = (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| (row, col)))
+            .map(|(row, col)| rect.min + Vec2::new(col as f32 + 0.5, row as f32 + 0.5) * cell)
+            .collect();
+        let values = potentials(&centers);
+        Self {
+            rect,
+            cell,
+            cols,
+            values,
+        }
+    }
+
+    /// Cells colored by the log of the well depth, scaled to the sampled
+    /// range and quantized into bands. A cell centered exactly on an
+    /// unsoftened body is infinitely deep; it gets the deepest band and is
+    /// left out of the range.
+    pub fn mesh(&self) -> Mesh {
+        let depth = |phi: f32| (-phi).max(f32::MIN_POSITIVE).ln();
+        let (min, max) = self
+            .values
+            .iter()
+            .map(|&phi| depth(phi))
+            .filter(|d| d.is_finite())
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), d| {
+                (lo.min(d), hi.max(d))
+            });
+        let range = (max - min).max(f32::EPSILON);
+        let mut mesh = Mesh::default();
+        for (i, &phi) in self.values.iter().enumerate() {
+            let t = (depth(phi) - min) / range;
+            let band = (t * POTENTIAL_BANDS).floor().min(POTENTIAL_BANDS - 1.0) / POTENTIAL_BANDS;
+            let min_corner = self.rect.min
+                + Vec2::new((i % self.cols) as f32, (i / self.cols) as f32) * self.cell;
+            let cell = Rect::from_min_size(min_corner, Vec2::splat(self.cell)).intersect(self.rect);
+            mesh.add_colored_rect(cell, ramp(band).gamma_multiply(0.5 * band + 0.1));
+        }
+        mesh
+    }
+}
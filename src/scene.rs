@@ -0,0 +1,148 @@
+//! Saving and loading scenes as JSON, and importing bodies from CSV.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use eframe::egui::Color32;
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+
+use crate::sim::Body;
+
+/// Everything needed to restore a configuration: the bodies, the camera and
+/// the physical constants the scene was built with.
+#[derive(Serialize, Deserialize)]
+pub struct Scene {
+    pub bodies: Vec<Body>,
+    pub camera_pos: Vector2<f32>,
+    pub zoom: f32,
+    pub g: f32,
+    pub softening: f32,
+}
+
+impl Scene {
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)
+    }
+
+    /// Reads and validates a scene written by [`Scene::save`].
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        let scene: Scene = serde_json::from_str(&json)?;
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    /// Rejects values that would poison the simulation, like NaNs or
+    /// non-positive masses.
+    fn validate(&self) -> io::Result<()> {
+        let invalid = |msg: String| Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        if !(is_finite(self.camera_pos) && self.zoom.is_finite() && self.zoom > 0.0) {
+            return invalid("invalid camera".to_string());
+        }
+        if !(self.g.is_finite() && self.softening.is_finite() && self.softening >= 0.0) {
+            return invalid("invalid physics settings".to_string());
+        }
+        if let Some(i) = self.bodies.iter().position(|b| !is_sane(b)) {
+            return invalid(format!("invalid body #{i}"));
+        }
+        Ok(())
+    }
+}
+
+/// Bodies copied to the clipboard, as JSON tagged with a field name that
+/// other applications' text won't have.
+#[derive(Serialize, Deserialize)]
+pub struct Clipboard {
+    gravisim_bodies: Vec<Body>,
+}
+
+impl Clipboard {
+    pub fn to_text(bodies: &[Body]) -> String {
+        let clipboard = Clipboard {
+            gravisim_bodies: bodies.to_vec(),
+        };
+        serde_json::to_string(&clipboard).unwrap_or_default()
+    }
+
+    /// Bodies in pasted `text`, with fresh IDs, or `None` if it isn't a
+    /// valid copy of bodies.
+    pub fn from_text(text: &str) -> Option<Vec<Body>> {
+        let clipboard: Clipboard = serde_json::from_str(text).ok()?;
+        let bodies = clipboard.gravisim_bodies;
+        (!bodies.is_empty() && bodies.iter().all(is_sane)).then_some(bodies)
+    }
+}
+
+/// Whether `body` has finite state and a positive mass and radius.
+fn is_sane(body: &Body) -> bool {
+    is_finite(body.pos)
+        && is_finite(body.vel)
+        && body.mass.is_finite()
+        && body.mass > 0.0
+        && body.radius.is_finite()
+        && body.radius > 0.0
+}
+
+fn is_finite(v: Vector2<f32>) -> bool {
+    v.x.is_finite() && v.y.is_finite()
+}
+
+/// Reads bodies from a CSV file of `x,y,vx,vy,mass,radius[,color]` rows,
+/// with the color as `#rrggbb`. A header row of column names, blank lines
+/// and lines starting with `#` are skipped. Errors name the offending line.
+pub fn import_csv(path: &Path) -> io::Result<Vec<Body>> {
+    let text = fs::read_to_string(path)?;
+    let mut bodies = Vec::new();
+    let mut first_row = true;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        // Tolerate a header naming the columns in place of the first row,
+        // but only if no field in it is a number: a mistyped first body
+        // should be reported, not silently dropped
+        if std::mem::take(&mut first_row) && fields.iter().all(|f| f.parse::<f32>().is_err()) {
+            continue;
+        }
+        let body = parse_body(&fields).map_err(|msg| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {msg}", i + 1))
+        })?;
+        bodies.push(body);
+    }
+    Ok(bodies)
+}
+
+fn parse_body(fields: &[&str]) -> Result<Body, String> {
+    if !(6..=7).contains(&fields.len()) {
+        return Err(format!("expected 6 or 7 fields, found {}", fields.len()));
+    }
+    let number = |index: usize, name: &str| {
+        fields[index]
+            .parse::<f32>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| format!("invalid {name} {:?}", fields[index]))
+    };
+    let pos = Vector2::new(number(0, "x")?, number(1, "y")?);
+    let vel = Vector2::new(number(2, "vx")?, number(3, "vy")?);
+    let mass = number(4, "mass")?;
+    let radius = number(5, "radius")?;
+    if mass <= 0.0 {
+        return Err("mass must be positive".to_string());
+    }
+    if radius <= 0.0 {
+        return Err("radius must be positive".to_string());
+    }
+    let color = match fields.get(6) {
+        Some(hex) => Color32::from_hex(hex).map_err(|_| format!("invalid color {hex:?}"))?,
+        None => Body::DEFAULT_COLOR,
+    };
+    let mut body = Body::new(pos, vel, mass / (radius * radius), radius, color);
+    body.mass = mass;
+    Ok(body)
+}
@@ -0,0 +1,1287 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use eframe::egui::Color32;
+use nalgebra::Vector2;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::barnes_hut::QuadTree;
+use crate::broadphase;
+
+/// Default gravitational constant, in world units per second squared. Chosen
+/// so that the attraction roughly matches the old per-frame impulse at 60 fps.
+pub const DEFAULT_G: f32 = 0.03;
+
+static NEXT_BODY_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Returns a fresh, never-reused body ID.
+fn next_body_id() -> u64 {
+    NEXT_BODY_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Body {
+    /// Stable identity that survives removals and reordering of `bodies`.
+    /// Bodies loaded from a file get fresh IDs.
+    #[serde(skip, default = "next_body_id")]
+    pub id: u64,
+    pub pos: Vector2<f32>,
+    pub vel: Vector2<f32>,
+    /// Net gravitational acceleration from the last force evaluation. Reused
+    /// by Verlet and drawn by the acceleration overlay.
+    #[serde(skip)]
+    pub acc: Vector2<f32>,
+    pub mass: f32,
+    pub radius: f32,
+    pub color: Color32,
+    /// Recent positions, oldest first, for drawing orbit trails.
+    #[serde(skip)]
+    pub trail: VecDeque<Vector2<f32>>,
+    /// Pinned in place: neither gravity nor collisions move it, though it
+    /// still attracts everything else.
+    #[serde(default)]
+    pub fixed: bool,
+    /// Collision heating: kinetic energy lost in impacts per unit mass,
+    /// cooling exponentially over `COOLING_TIME`. Only used for display.
+    #[serde(skip)]
+    pub temp: f32,
+}
+
+/// Time constant of the exponential cooling of `Body::temp`, in seconds.
+const COOLING_TIME: f32 = 1.0;
+
+/// ID given to throwaway bodies that never enter the real simulation.
+const PROVISIONAL_ID: u64 = u64::MAX;
+
+impl Body {
+    pub const DEFAULT_COLOR: Color32 = Color32::from_rgb(200, 200, 255);
+
+    pub fn new(
+        pos: Vector2<f32>,
+        vel: Vector2<f32>,
+        density: f32,
+        size: f32,
+        color: Color32,
+    ) -> Self {
+        Self::with_id(next_body_id(), pos, vel, density, size, color)
+    }
+
+    fn with_id(
+        id: u64,
+        pos: Vector2<f32>,
+        vel: Vector2<f32>,
+        density: f32,
+        size: f32,
+        color: Color32,
+    ) -> Self {
+        let radius = size;
+        let mass = density * radius * radius;
+        Self {
+            id,
+            pos,
+            vel,
+            acc: Vector2::zeros(),
+            mass,
+            radius,
+            color,
+            trail: VecDeque::new(),
+            fixed: false,
+            temp: 0.0,
+        }
+    }
+}
+
+/// Velocity for a circular orbit around a point mass `central_mass` from a
+/// position `offset` relative to it: `sqrt(G M / r)` perpendicular to the
+/// radius vector.
+pub fn circular_velocity(g: f32, central_mass: f32, offset: Vector2<f32>) -> Vector2<f32> {
+    let r = offset.norm();
+    if r <= 0.0 {
+        return Vector2::zeros();
+    }
+    let speed = (g * central_mass / r).sqrt();
+    Vector2::new(-offset.y, offset.x) / r * speed
+}
+
+/// Distance from `primary` inside which its tides would pull `satellite`
+/// apart: the rigid-body Roche limit `R (2 ρ_M / ρ_m)^(1/3)`. Written in terms
+/// of masses, `r_m (2 M / m)^(1/3)`, so it holds for this simulation's
+/// point-mass gravity even though body densities are per unit area.
+pub fn roche_limit(primary: &Body, satellite: &Body) -> f32 {
+    if satellite.mass <= 0.0 {
+        return 0.0;
+    }
+    satellite.radius * (2.0 * primary.mass / satellite.mass).cbrt()
+}
+
+/// Gravitational force exerted on a body of mass `m1` at `from` by a body of
+/// mass `m2` at `to`, for gravitational constant `g`. The force on the second
+/// body is the negation.
+///
+/// Uses Plummer softening: the separation is treated as `sqrt(d² + ε²)`, so
+/// close encounters are smoothly weakened instead of blowing up.
+pub fn gravity_force(
+    g: f32,
+    from: Vector2<f32>,
+    to: Vector2<f32>,
+    m1: f32,
+    m2: f32,
+    softening: f32,
+) -> Vector2<f32> {
+    let dir = to - from;
+    let soft_dist_sq = dir.norm_squared() + softening * softening;
+    if soft_dist_sq <= f32::EPSILON {
+        return Vector2::zeros();
+    }
+    // One square root for both the direction and the inverse-square falloff.
+    let inv_dist = 1.0 / soft_dist_sq.sqrt();
+    dir * (g * m1 * m2 * inv_dist / soft_dist_sq)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Integrator {
+    /// Semi-implicit Euler: cheap, but leaks energy on long orbits.
+    Euler,
+    /// Velocity Verlet: symplectic, keeps orbits closed.
+    #[default]
+    Verlet,
+    /// Classic fourth-order Runge-Kutta. Most accurate per step, but costs
+    /// four gravity evaluations per step instead of one.
+    Rk4,
+}
+
+impl Integrator {
+    pub const ALL: [Integrator; 3] = [Integrator::Euler, Integrator::Verlet, Integrator::Rk4];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Integrator::Euler => "Euler",
+            Integrator::Verlet => "Verlet",
+            Integrator::Rk4 => "RK4",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GravitySolver {
+    /// Direct O(n²) sum over every pair.
+    #[default]
+    Exact,
+    /// Barnes-Hut quadtree approximation, O(n log n).
+    BarnesHut,
+}
+
+impl GravitySolver {
+    pub const ALL: [GravitySolver; 2] = [GravitySolver::Exact, GravitySolver::BarnesHut];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GravitySolver::Exact => "Exact",
+            GravitySolver::BarnesHut => "Barnes-Hut",
+        }
+    }
+}
+
+/// Total mechanical energy of the system, accumulated in `f64` so the readout
+/// isn't dominated by rounding for large scenes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Energy {
+    pub kinetic: f64,
+    pub potential: f64,
+}
+
+impl Energy {
+    pub fn total(&self) -> f64 {
+        self.kinetic + self.potential
+    }
+
+    /// Kinetic over (the magnitude of) potential energy, which settles near
+    /// ½ once a bound system has relaxed. `None` without any potential.
+    pub fn virial_ratio(&self) -> Option<f64> {
+        (self.potential != 0.0).then(|| self.kinetic / self.potential.abs())
+    }
+
+    /// The virial quantity `2K + U`, which trends toward zero as a bound
+    /// system reaches equilibrium.
+    pub fn virial(&self) -> f64 {
+        2.0 * self.kinetic + self.potential
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CollisionMode {
+    /// Overlapping bodies bounce apart, losing energy according to the
+    /// coefficient of restitution.
+    Bounce,
+    /// Overlapping bodies merge into one (perfectly inelastic accretion).
+    #[default]
+    Merge,
+}
+
+impl CollisionMode {
+    pub const ALL: [CollisionMode; 2] = [CollisionMode::Bounce, CollisionMode::Merge];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CollisionMode::Bounce => "Bounce",
+            CollisionMode::Merge => "Merge",
+        }
+    }
+}
+
+/// What happens to bodies at the edges of the world rectangle.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Boundary {
+    /// Unbounded space.
+    #[default]
+    Open,
+    /// Bodies leaving one edge reappear at the opposite one. Only positions
+    /// wrap; gravity still acts along the direct line between bodies.
+    Wrap,
+    /// Bodies bounce off the edges, losing speed according to the
+    /// coefficient of restitution.
+    Walls,
+}
+
+impl Boundary {
+    pub const ALL: [Boundary; 3] = [Boundary::Open, Boundary::Wrap, Boundary::Walls];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Boundary::Open => "Open space",
+            Boundary::Wrap => "Wrap around",
+            Boundary::Walls => "Walls",
+        }
+    }
+}
+
+/// Default distance from the center of mass past which bodies are culled.
+pub const DEFAULT_CULL_DISTANCE: f32 = 20_000.0;
+
+/// Default width and height of the world rectangle for bounded modes.
+pub const DEFAULT_WORLD_SIZE: f32 = 2000.0;
+
+/// Two bodies that merged during a step, as they were just before merging.
+#[derive(Clone)]
+pub struct MergeRecord {
+    /// ID of the merged body (the heavier part's ID).
+    pub result: u64,
+    pub parts: [Body; 2],
+}
+
+/// Running totals of collisions since the counters were last reset.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CollisionStats {
+    /// Impacts resolved by bouncing.
+    pub bounces: u64,
+    pub merges: u64,
+    /// Kinetic energy dissipated by merges and inelastic bounces.
+    pub energy_lost: f64,
+}
+
+impl CollisionStats {
+    pub fn total(&self) -> u64 {
+        self.bounces + self.merges
+    }
+}
+
+/// Specific orbital energies within this fraction of the local potential
+/// `μ/r` of zero count as parabolic.
+const PARABOLIC_TOLERANCE: f32 = 1e-3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrbitKind {
+    Elliptical,
+    Parabolic,
+    Hyperbolic,
+}
+
+impl OrbitKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            OrbitKind::Elliptical => "Elliptical (bound)",
+            OrbitKind::Parabolic => "Parabolic",
+            OrbitKind::Hyperbolic => "Hyperbolic (unbound)",
+        }
+    }
+}
+
+/// Two-body orbital elements of a body around another.
+#[derive(Clone, Copy, Debug)]
+pub struct Orbit {
+    /// ID of the body being orbited.
+    pub central: u64,
+    pub kind: OrbitKind,
+    /// Negative for hyperbolic orbits, and unbounded near parabolic ones.
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    /// Orbital period in simulated seconds, for bound orbits.
+    pub period: Option<f32>,
+}
+
+/// Default Barnes-Hut opening angle.
+pub const DEFAULT_THETA: f32 = 0.5;
+
+/// Default Plummer softening length, in world units.
+pub const DEFAULT_SOFTENING: f32 = 2.0;
+
+#[derive(Clone)]
+pub struct Simulation {
+    pub bodies: Vec<Body>,
+    /// Gravitational constant.
+    pub g: f32,
+    pub integrator: Integrator,
+    /// Plummer softening length `ε`.
+    pub softening: f32,
+    pub solver: GravitySolver,
+    /// Barnes-Hut opening angle; smaller is more accurate but slower.
+    pub theta: f32,
+    pub collision_mode: CollisionMode,
+    /// Coefficient of restitution for bounces: 1 is perfectly elastic, 0
+    /// leaves the bodies with no relative velocity along the impact normal.
+    pub restitution: f32,
+    /// Merges since the owner last drained this list.
+    pub merges: Vec<MergeRecord>,
+    pub collisions: CollisionStats,
+    pub boundary: Boundary,
+    /// Size of the world rectangle, centered on the origin.
+    pub world_size: Vector2<f32>,
+    /// Remove bodies that stray farther than `cull_distance` from the
+    /// center of mass, such as ejected ones flying off forever.
+    pub cull_escapees: bool,
+    pub cull_distance: f32,
+    /// Bodies removed by culling since the owner last reset this.
+    pub culled: u64,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self {
+            bodies: Vec::new(),
+            g: DEFAULT_G,
+            integrator: Integrator::default(),
+            softening: DEFAULT_SOFTENING,
+            solver: GravitySolver::default(),
+            theta: DEFAULT_THETA,
+            collision_mode: CollisionMode::default(),
+            restitution: 1.0,
+            merges: Vec::new(),
+            collisions: CollisionStats::default(),
+            boundary: Boundary::default(),
+            world_size: Vector2::repeat(DEFAULT_WORLD_SIZE),
+            cull_escapees: false,
+            cull_distance: DEFAULT_CULL_DISTANCE,
+            culled: 0,
+        }
+    }
+}
+
+impl Simulation {
+    /// Forward-simulates a copy of the scene with an extra body spawned at
+    /// `pos` with velocity `vel`, and returns the positions that body passes
+    /// through over `steps` steps of `dt`. The prediction stops early if the
+    /// body is absorbed in a merge. The real state is left untouched.
+    pub fn predict_path(
+        &self,
+        pos: Vector2<f32>,
+        vel: Vector2<f32>,
+        density: f32,
+        size: f32,
+        steps: usize,
+        dt: f32,
+    ) -> Vec<Vector2<f32>> {
+        let mut preview = self.clone();
+        for body in &mut preview.bodies {
+            body.trail.clear();
+        }
+        preview.bodies.push(Body::with_id(
+            PROVISIONAL_ID,
+            pos,
+            vel,
+            density,
+            size,
+            Body::DEFAULT_COLOR,
+        ));
+        preview.compute_accelerations();
+
+        let mut path = vec![pos];
+        for _ in 0..steps {
+            preview.step(dt);
+            match preview.find(PROVISIONAL_ID) {
+                Some(index) => path.push(preview.bodies[index].pos),
+                None => break,
+            }
+        }
+        path
+    }
+
+    /// Index of the body with the given `id`, if it still exists.
+    pub fn find(&self, id: u64) -> Option<usize> {
+        self.bodies.iter().position(|b| b.id == id)
+    }
+
+    /// Removes the body with the given `id`, returning it if it existed.
+    pub fn remove(&mut self, id: u64) -> Option<Body> {
+        let index = self.find(id)?;
+        Some(self.bodies.remove(index))
+    }
+
+    /// Index of the topmost body covering `point`. Bodies smaller than
+    /// `min_radius` are treated as that size so tiny ones can still be hit.
+    pub fn body_at(&self, point: Vector2<f32>, min_radius: f32) -> Option<usize> {
+        self.bodies.iter().rposition(|b| {
+            let r = b.radius.max(min_radius);
+            (b.pos - point).norm_squared() <= r * r
+        })
+    }
+
+    /// Index of the body whose gravitational pull is strongest at `point`,
+    /// ignoring the body at index `skip`.
+    pub fn dominant_attractor(&self, point: Vector2<f32>, skip: Option<usize>) -> Option<usize> {
+        let pull = |b: &Body| b.mass / ((b.pos - point).norm_squared() + self.softening.powi(2));
+        (0..self.bodies.len())
+            .filter(|&i| Some(i) != skip)
+            .max_by(|&i, &j| pull(&self.bodies[i]).total_cmp(&pull(&self.bodies[j])))
+    }
+
+    /// Keplerian orbit of the body at `index` around its dominant attractor,
+    /// treating the two as an isolated pair. `None` if there is no other body.
+    pub fn orbit_of(&self, index: usize) -> Option<Orbit> {
+        let body = &self.bodies[index];
+        let central_index = self.dominant_attractor(body.pos, Some(index))?;
+        let central = &self.bodies[central_index];
+        let mu = self.g * (central.mass + body.mass);
+        let r = body.pos - central.pos;
+        let v = body.vel - central.vel;
+        let dist = r.norm();
+        if dist <= 0.0 || mu <= 0.0 {
+            return None;
+        }
+        // Vis-viva: the specific orbital energy fixes the semi-major axis.
+        let energy = 0.5 * v.norm_squared() - mu / dist;
+        let angular_momentum = r.perp(&v);
+        let eccentricity = (1.0 + 2.0 * energy * angular_momentum.powi(2) / (mu * mu))
+            .max(0.0)
+            .sqrt();
+        let kind = if energy.abs() < PARABOLIC_TOLERANCE * mu / dist {
+            OrbitKind::Parabolic
+        } else if energy < 0.0 {
+            OrbitKind::Elliptical
+        } else {
+            OrbitKind::Hyperbolic
+        };
+        let semi_major_axis = -mu / (2.0 * energy);
+        let period = (kind == OrbitKind::Elliptical)
+            .then(|| std::f32::consts::TAU * (semi_major_axis.powi(3) / mu).sqrt());
+        Some(Orbit {
+            central: central.id,
+            kind,
+            semi_major_axis,
+            eccentricity,
+            period,
+        })
+    }
+
+    /// The heavier body dominating the one at `index` and its Roche limit
+    /// for it, or `None` if nothing heavier is pulling on it.
+    pub fn roche_limit_of(&self, index: usize) -> Option<(usize, f32)> {
+        let body = &self.bodies[index];
+        let primary = self.dominant_attractor(body.pos, Some(index))?;
+        (self.bodies[primary].mass > body.mass)
+            .then(|| (primary, roche_limit(&self.bodies[primary], body)))
+    }
+
+    /// Velocity that puts a body of `mass` at `pos` on a circular orbit
+    /// around the dominant attractor there, moving along with it. `None` if
+    /// the scene is empty.
+    pub fn orbit_velocity(&self, pos: Vector2<f32>, mass: f32) -> Option<Vector2<f32>> {
+        let center = self.dominant_attractor(pos, None)?;
+        Some(self.orbit_velocity_around(center, pos, mass))
+    }
+
+    /// Velocity that puts a body of `mass` at `pos` on a circular orbit
+    /// around the body at index `center`, moving along with it.
+    pub fn orbit_velocity_around(
+        &self,
+        center: usize,
+        pos: Vector2<f32>,
+        mass: f32,
+    ) -> Vector2<f32> {
+        let center = &self.bodies[center];
+        center.vel + circular_velocity(self.g, center.mass + mass, pos - center.pos)
+    }
+
+    /// Velocity of the center of mass, or `None` if there is no mass.
+    pub fn center_of_mass_velocity(&self) -> Option<Vector2<f32>> {
+        let total_mass: f32 = self.bodies.iter().map(|b| b.mass).sum();
+        (total_mass > 0.0).then(|| self.momentum() / total_mass)
+    }
+
+    /// Mass-weighted centroid of all bodies, or `None` if there is no mass.
+    pub fn center_of_mass(&self) -> Option<Vector2<f32>> {
+        let total_mass: f32 = self.bodies.iter().map(|b| b.mass).sum();
+        if total_mass <= 0.0 {
+            return None;
+        }
+        let weighted: Vector2<f32> = self.bodies.iter().map(|b| b.pos * b.mass).sum();
+        Some(weighted / total_mass)
+    }
+
+    /// Total linear momentum `Σ m v`.
+    pub fn momentum(&self) -> Vector2<f32> {
+        self.bodies.iter().map(|b| b.vel * b.mass).sum()
+    }
+
+    /// Total angular momentum `Σ m (r × v)` about the center of mass, with
+    /// positions and velocities taken relative to it. In 2D this is the
+    /// scalar z component; positive is counterclockwise in world axes.
+    pub fn angular_momentum(&self) -> f64 {
+        let (Some(com), Some(com_vel)) = (self.center_of_mass(), self.center_of_mass_velocity())
+        else {
+            return 0.0;
+        };
+        self.bodies
+            .iter()
+            .map(|b| {
+                let r = b.pos - com;
+                let v = b.vel - com_vel;
+                b.mass as f64 * (r.x as f64 * v.y as f64 - r.y as f64 * v.x as f64)
+            })
+            .sum()
+    }
+
+    /// Kinetic energy `½ Σ m v²` and softened potential energy
+    /// `-G Σ m_i m_j / sqrt(d² + ε²)` over all pairs. The potential is O(n²).
+    pub fn energy(&self) -> Energy {
+        let kinetic = self
+            .bodies
+            .iter()
+            .map(|b| 0.5 * b.mass as f64 * b.vel.norm_squared() as f64)
+            .sum();
+        let eps_sq = (self.softening * self.softening) as f64;
+        let mut potential = 0.0;
+        for (i, a) in self.bodies.iter().enumerate() {
+            for b in &self.bodies[i + 1..] {
+                let dist = ((b.pos - a.pos).norm_squared() as f64 + eps_sq).sqrt();
+                if dist > 0.0 {
+                    potential -= self.g as f64 * a.mass as f64 * b.mass as f64 / dist;
+                }
+            }
+        }
+        Energy { kinetic, potential }
+    }
+
+    /// Shortest time, over all pairs of bodies, for a pair to cover its own
+    /// separation at its current relative speed, `|Δx| / |Δv|`. `None` if no
+    /// two bodies are moving relative to each other. This is O(n²).
+    pub fn closest_encounter_time(&self) -> Option<f32> {
+        let mut shortest_sq = f32::INFINITY;
+        for (i, a) in self.bodies.iter().enumerate() {
+            for b in &self.bodies[i + 1..] {
+                let speed_sq = (b.vel - a.vel).norm_squared();
+                if speed_sq > 0.0 {
+                    shortest_sq = shortest_sq.min((b.pos - a.pos).norm_squared() / speed_sq);
+                }
+            }
+        }
+        shortest_sq.is_finite().then(|| shortest_sq.sqrt())
+    }
+
+    /// Gravitational acceleration a massless test particle would feel at
+    /// `point`.
+    pub fn field_at(&self, point: Vector2<f32>) -> Vector2<f32> {
+        self.bodies
+            .iter()
+            .map(|b| gravity_force(self.g, point, b.pos, 1.0, b.mass, self.softening))
+            .sum()
+    }
+
+    /// Principal stretching axis (a unit vector) and rate of the tidal field
+    /// acting on the body at `index`: the largest eigenvalue of the field's
+    /// gradient from all other bodies, in acceleration per unit length.
+    /// Neighbouring points of the body drift apart along the axis at about
+    /// `rate · distance`. `None` if nothing stretches it.
+    pub fn tidal_stretch(&self, index: usize) -> Option<(Vector2<f32>, f32)> {
+        let point = self.bodies[index].pos;
+        let eps_sq = self.softening * self.softening;
+        // Gradient of the softened field g = Σ G m d / s^(3/2), with d the
+        // offset to each body and s = |d|² + ε², as the symmetric matrix
+        // [[xx, xy], [xy, yy]].
+        let (mut xx, mut xy, mut yy) = (0.0, 0.0, 0.0);
+        for (i, b) in self.bodies.iter().enumerate() {
+            let d = b.pos - point;
+            let s = d.norm_squared() + eps_sq;
+            if i == index || s <= f32::EPSILON {
+                continue;
+            }
+            let k = self.g * b.mass / (s * s.sqrt());
+            let k3 = 3.0 * k / s;
+            xx += k3 * d.x * d.x - k;
+            xy += k3 * d.x * d.y;
+            yy += k3 * d.y * d.y - k;
+        }
+        let mean = 0.5 * (xx + yy);
+        let rate = mean + (0.25 * (xx - yy).powi(2) + xy * xy).sqrt();
+        if !(rate > 0.0 && rate.is_finite()) {
+            return None;
+        }
+        let axis = if xy.abs() > f32::EPSILON * rate {
+            Vector2::new(xy, rate - xx).normalize()
+        } else if xx >= yy {
+            Vector2::x()
+        } else {
+            Vector2::y()
+        };
+        Some((axis, rate))
+    }
+
+    /// Softened gravitational potential per unit mass at `point`, summed
+    /// over all bodies. Without softening it is infinite at a body's center.
+    fn potential_at(&self, point: Vector2<f32>) -> f32 {
+        let eps_sq = self.softening * self.softening;
+        -self.g
+            * self
+                .bodies
+                .iter()
+                .map(|b| b.mass / ((b.pos - point).norm_squared() + eps_sq).sqrt())
+                .sum::<f32>()
+    }
+
+    /// The potential at each of `points`, which are spread over threads with
+    /// the `parallel` feature.
+    pub fn potentials_at(&self, points: &[Vector2<f32>]) -> Vec<f32> {
+        #[cfg(feature = "parallel")]
+        let phi = points.par_iter().map(|&p| self.potential_at(p)).collect();
+        #[cfg(not(feature = "parallel"))]
+        let phi = points.iter().map(|&p| self.potential_at(p)).collect();
+        phi
+    }
+
+    /// Advances every body by `dt` seconds using the selected integrator.
+    /// Anything that should happen once per step (such as collisions) belongs
+    /// here, after integration, not inside the integrators' force evaluations.
+    pub fn step(&mut self, dt: f32) {
+        match self.integrator {
+            Integrator::Euler => self.step_euler(dt),
+            Integrator::Verlet => self.step_velocity_verlet(dt),
+            Integrator::Rk4 => self.step_rk4(dt),
+        }
+        self.cool(dt);
+        self.apply_boundary();
+        self.resolve_collisions();
+        if self.cull_escapees {
+            self.cull_distant();
+        }
+    }
+
+    /// Lets every body's collision heating decay for `dt` seconds.
+    fn cool(&mut self, dt: f32) {
+        let factor = (-dt / COOLING_TIME).exp();
+        for body in &mut self.bodies {
+            body.temp *= factor;
+        }
+    }
+
+    /// Removes bodies beyond `cull_distance` from the center of mass.
+    fn cull_distant(&mut self) {
+        let Some(com) = self.center_of_mass() else {
+            return;
+        };
+        let max_sq = self.cull_distance * self.cull_distance;
+        let before = self.bodies.len();
+        self.bodies
+            .retain(|b| (b.pos - com).norm_squared() <= max_sq);
+        self.culled += (before - self.bodies.len()) as u64;
+    }
+
+    /// Brings bodies that left the world rectangle back inside it. Fixed
+    /// bodies stay wherever they were pinned.
+    fn apply_boundary(&mut self) {
+        let half = self.world_size * 0.5;
+        let mut moved = false;
+        match self.boundary {
+            Boundary::Open => {}
+            Boundary::Wrap => {
+                for body in self.bodies.iter_mut().filter(|b| !b.fixed) {
+                    for axis in 0..2 {
+                        let (p, h) = (body.pos[axis], half[axis]);
+                        // Only touch bodies that are outside, so the round
+                        // trip through the shifted range can't nudge the rest.
+                        if p.abs() > h && p.is_finite() {
+                            body.pos[axis] = (p + h).rem_euclid(2.0 * h) - h;
+                            // Don't draw a trail segment across the world.
+                            body.trail.clear();
+                            moved = true;
+                        }
+                    }
+                }
+            }
+            Boundary::Walls => {
+                for body in self.bodies.iter_mut().filter(|b| !b.fixed) {
+                    for axis in 0..2 {
+                        // Keep the whole body inside, unless it's too big to fit.
+                        let limit = (half[axis] - body.radius).max(0.0);
+                        let p = body.pos[axis];
+                        if p.abs() <= limit {
+                            continue;
+                        }
+                        body.pos[axis] = p.clamp(-limit, limit);
+                        moved = true;
+                        let v = body.vel[axis];
+                        if v * p > 0.0 {
+                            body.vel[axis] = -v * self.restitution;
+                            let lost = 0.5 * body.mass * v * v * (1.0 - self.restitution.powi(2));
+                            self.collisions.energy_lost += f64::from(lost);
+                            heat(body, lost, body.mass);
+                        }
+                    }
+                }
+            }
+        }
+        // The stored accelerations were evaluated before the move.
+        if moved {
+            self.compute_accelerations();
+        }
+    }
+
+    fn resolve_collisions(&mut self) {
+        match self.collision_mode {
+            CollisionMode::Merge => self.merge_overlapping(),
+            CollisionMode::Bounce => {
+                for (i, j) in self.collision_candidates() {
+                    let (left, right) = self.bodies.split_at_mut(j);
+                    let (a, b) = (&mut left[i], &mut right[0]);
+                    if let Some(lost) = bounce(a, b, self.restitution) {
+                        self.collisions.bounces += 1;
+                        self.collisions.energy_lost += f64::from(lost);
+                        let mass = a.mass + b.mass;
+                        heat(a, lost, mass);
+                        heat(b, lost, mass);
+                    }
+                }
+            }
+        }
+    }
+
+    fn step_euler(&mut self, dt: f32) {
+        self.compute_accelerations();
+        for body in self.bodies.iter_mut().filter(|b| !b.fixed) {
+            body.vel += body.acc * dt;
+            body.pos += body.vel * dt;
+        }
+    }
+
+    /// Velocity Verlet step. Relies on `acc` holding the accelerations from
+    /// the end of the previous step, so only one force evaluation is needed.
+    fn step_velocity_verlet(&mut self, dt: f32) {
+        for body in self.bodies.iter_mut().filter(|b| !b.fixed) {
+            body.pos += body.vel * dt + body.acc * (0.5 * dt * dt);
+        }
+        let acc_old: Vec<Vector2<f32>> = self.bodies.iter().map(|b| b.acc).collect();
+        self.compute_accelerations();
+        for (body, acc_old) in self.bodies.iter_mut().zip(acc_old) {
+            if !body.fixed {
+                body.vel += (acc_old + body.acc) * (0.5 * dt);
+            }
+        }
+    }
+
+    /// RK4 step over the combined position/velocity state of all bodies.
+    /// Fixed bodies are held still, even in the intermediate stages.
+    fn step_rk4(&mut self, dt: f32) {
+        let x0: Vec<Vector2<f32>> = self.bodies.iter().map(|b| b.pos).collect();
+        let v0: Vec<Vector2<f32>> = self
+            .bodies
+            .iter()
+            .map(|b| if b.fixed { Vector2::zeros() } else { b.vel })
+            .collect();
+
+        let k1x = v0.clone();
+        let k1v = self.accelerations_at(&x0);
+        let k2x = offset(&v0, &k1v, 0.5 * dt);
+        let k2v = self.accelerations_at(&offset(&x0, &k1x, 0.5 * dt));
+        let k3x = offset(&v0, &k2v, 0.5 * dt);
+        let k3v = self.accelerations_at(&offset(&x0, &k2x, 0.5 * dt));
+        let k4x = offset(&v0, &k3v, dt);
+        let k4v = self.accelerations_at(&offset(&x0, &k3x, dt));
+
+        for (i, body) in self.bodies.iter_mut().enumerate() {
+            if body.fixed {
+                continue;
+            }
+            body.pos += (k1x[i] + 2.0 * k2x[i] + 2.0 * k3x[i] + k4x[i]) * (dt / 6.0);
+            body.vel += (k1v[i] + 2.0 * k2v[i] + 2.0 * k3v[i] + k4v[i]) * (dt / 6.0);
+        }
+        // Leave `acc` at the end-of-step state, as Verlet expects.
+        self.compute_accelerations();
+    }
+
+    /// Recomputes `acc` for every body from the pairwise gravity.
+    pub fn compute_accelerations(&mut self) {
+        let positions: Vec<Vector2<f32>> = self.bodies.iter().map(|b| b.pos).collect();
+        let acc = self.accelerations_at(&positions);
+        for (body, acc) in self.bodies.iter_mut().zip(acc) {
+            body.acc = acc;
+        }
+    }
+
+    /// Gravitational acceleration on each body if the bodies were placed at
+    /// `positions` (indexed like `bodies`).
+    /// Fixed bodies get no acceleration.
+    fn accelerations_at(&self, positions: &[Vector2<f32>]) -> Vec<Vector2<f32>> {
+        let mut acc = self.gravity_at(positions);
+        for (acc, body) in acc.iter_mut().zip(&self.bodies) {
+            if body.fixed {
+                *acc = Vector2::zeros();
+            }
+        }
+        acc
+    }
+
+    fn gravity_at(&self, positions: &[Vector2<f32>]) -> Vec<Vector2<f32>> {
+        match self.solver {
+            #[cfg(feature = "simd")]
+            GravitySolver::Exact => {
+                let masses: Vec<f32> = self.bodies.iter().map(|b| b.mass).collect();
+                crate::simd::accelerations(positions, &masses, self.g, self.softening)
+            }
+            #[cfg(not(feature = "simd"))]
+            GravitySolver::Exact => self.exact_accelerations(positions),
+            GravitySolver::BarnesHut => {
+                let masses: Vec<f32> = self.bodies.iter().map(|b| b.mass).collect();
+                let tree = QuadTree::build(positions, &masses);
+                let accel = |(i, pos): (usize, &Vector2<f32>)| {
+                    tree.acceleration(i, *pos, self.g, self.theta, self.softening)
+                };
+                #[cfg(feature = "parallel")]
+                let acc = positions.par_iter().enumerate().map(accel).collect();
+                #[cfg(not(feature = "parallel"))]
+                let acc = positions.iter().enumerate().map(accel).collect();
+                acc
+            }
+        }
+    }
+
+    /// Each body sums its own acceleration over every other body. This does
+    /// twice the pair work of the serial version but needs no shared writes.
+    /// With the `simd` feature this scalar version is kept as the reference
+    /// the batched one is checked against.
+    #[cfg(feature = "parallel")]
+    #[cfg_attr(feature = "simd", allow(dead_code))]
+    fn exact_accelerations(&self, positions: &[Vector2<f32>]) -> Vec<Vector2<f32>> {
+        positions
+            .par_iter()
+            .enumerate()
+            .map(|(i, &pos)| {
+                let mut acc = Vector2::zeros();
+                for (j, (&other, body)) in positions.iter().zip(&self.bodies).enumerate() {
+                    if i != j {
+                        acc += gravity_force(self.g, pos, other, 1.0, body.mass, self.softening);
+                    }
+                }
+                acc
+            })
+            .collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    #[cfg_attr(feature = "simd", allow(dead_code))]
+    fn exact_accelerations(&self, positions: &[Vector2<f32>]) -> Vec<Vector2<f32>> {
+        let mut acc = vec![Vector2::zeros(); positions.len()];
+        for i in 0..positions.len() {
+            let mi = self.bodies[i].mass;
+            for j in (i + 1)..positions.len() {
+                let mj = self.bodies[j].mass;
+                let force =
+                    gravity_force(self.g, positions[i], positions[j], mi, mj, self.softening);
+                acc[i] += force / mi;
+                acc[j] -= force / mj;
+            }
+        }
+        acc
+    }
+
+    /// Pairs of bodies close enough that they might overlap, from a spatial
+    /// hash rebuilt from the current positions.
+    fn collision_candidates(&self) -> Vec<(usize, usize)> {
+        let positions: Vec<Vector2<f32>> = self.bodies.iter().map(|b| b.pos).collect();
+        let radii: Vec<f32> = self.bodies.iter().map(|b| b.radius).collect();
+        broadphase::candidate_pairs(&positions, &radii)
+    }
+
+    /// Merges every overlapping pair. Pairs are collected first and applied
+    /// afterwards so the body list isn't mutated mid-scan; a body takes part
+    /// in at most one merge per step, and chains finish on later steps.
+    fn merge_overlapping(&mut self) {
+        let mut claimed = vec![false; self.bodies.len()];
+        let mut merges = Vec::new();
+        for (i, j) in self.collision_candidates() {
+            if !claimed[i] && !claimed[j] && overlapping(&self.bodies[i], &self.bodies[j]) {
+                claimed[i] = true;
+                claimed[j] = true;
+                merges.push((i, j));
+            }
+        }
+        if merges.is_empty() {
+            return;
+        }
+
+        self.collisions.merges += merges.len() as u64;
+        let mut removed = vec![false; self.bodies.len()];
+        for (i, j) in merges {
+            let (a, b) = (&self.bodies[i], &self.bodies[j]);
+            // A perfectly inelastic collision keeps only the center-of-mass
+            // motion, losing all the kinetic energy of the relative motion.
+            let relative_speed_sq = (a.vel - b.vel).norm_squared();
+            let lost = 0.5 * reduced_mass(a, b) * relative_speed_sq;
+            self.collisions.energy_lost += f64::from(lost);
+            let mut result = merged(a, b);
+            let mass = result.mass;
+            heat(&mut result, lost, mass);
+            let parts = [self.bodies[i].clone(), self.bodies[j].clone()];
+            self.merges.push(MergeRecord {
+                result: result.id,
+                parts,
+            });
+            self.bodies[i] = result;
+            removed[j] = true;
+        }
+        let mut keep = removed.into_iter().map(|r| !r);
+        self.bodies.retain(|_| keep.next().unwrap());
+    }
+}
+
+fn overlapping(a: &Body, b: &Body) -> bool {
+    let reach = a.radius + b.radius;
+    (b.pos - a.pos).norm_squared() < reach * reach
+}
+
+/// The body formed by perfectly inelastic accretion of `a` and `b`: mass and
+/// momentum are conserved, the area is preserved and the color and
+/// temperature are blended by mass. A fixed body acts as infinitely heavy:
+/// the result stays where it is and is fixed too.
+fn merged(a: &Body, b: &Body) -> Body {
+    let mass = a.mass + b.mass;
+    let share = if mass > 0.0 { b.mass / mass } else { 0.5 };
+    let t = match (a.fixed, b.fixed) {
+        (true, false) => 0.0,
+        (false, true) => 1.0,
+        _ => share,
+    };
+    // The heavier body absorbs the lighter one and keeps its identity.
+    let survivor = if a.mass >= b.mass { a } else { b };
+    Body {
+        id: survivor.id,
+        pos: a.pos.lerp(&b.pos, t),
+        vel: a.vel.lerp(&b.vel, t),
+        acc: a.acc.lerp(&b.acc, t),
+        mass,
+        radius: (a.radius * a.radius + b.radius * b.radius).sqrt(),
+        color: a.color.lerp_to_gamma(b.color, share),
+        trail: survivor.trail.clone(),
+        fixed: a.fixed || b.fixed,
+        temp: a.temp + (b.temp - a.temp) * share,
+    }
+}
+
+/// Warms `body` by its part in a collision that lost `energy`, spread evenly
+/// over the colliding `mass`.
+fn heat(body: &mut Body, energy: f32, mass: f32) {
+    if mass > 0.0 {
+        body.temp += energy / mass;
+    }
+}
+
+/// If `a` and `b` overlap, bounces them apart with an impulse along the line
+/// between their centers and separates them so they no longer overlap.
+/// Momentum is always conserved; kinetic energy only when `restitution` is 1.
+/// Returns the kinetic energy lost if an impulse was applied, i.e. if the
+/// bodies were approaching.
+fn bounce(a: &mut Body, b: &mut Body, restitution: f32) -> Option<f32> {
+    let delta = b.pos - a.pos;
+    let dist = delta.norm();
+    let overlap = a.radius + b.radius - dist;
+    let (inv_a, inv_b) = (inverse_mass(a), inverse_mass(b));
+    let inv_total = inv_a + inv_b;
+    if overlap <= 0.0 || inv_total <= 0.0 {
+        return None;
+    }
+    let normal = if dist > 0.0 {
+        delta / dist
+    } else {
+        Vector2::x()
+    };
+
+    // Push apart in inverse proportion to mass so the center of mass stays put.
+    a.pos -= normal * (overlap * inv_a / inv_total);
+    b.pos += normal * (overlap * inv_b / inv_total);
+
+    let approach = (a.vel - b.vel).dot(&normal);
+    if approach <= 0.0 {
+        return None;
+    }
+    let mu = reduced_mass(a, b);
+    let impulse = (1.0 + restitution) * approach * mu;
+    a.vel -= normal * (impulse * inv_a);
+    b.vel += normal * (impulse * inv_b);
+    // The normal relative speed drops from `approach` to `e·approach`.
+    Some(0.5 * mu * approach * approach * (1.0 - restitution * restitution))
+}
+
+/// `m1·m2 / (m1 + m2)`, the effective mass of the pair's relative motion.
+fn reduced_mass(a: &Body, b: &Body) -> f32 {
+    let inv_total = inverse_mass(a) + inverse_mass(b);
+    if inv_total > 0.0 {
+        1.0 / inv_total
+    } else {
+        0.0
+    }
+}
+
+/// `1 / m`, or zero for fixed (effectively infinitely heavy) and massless
+/// bodies.
+fn inverse_mass(body: &Body) -> f32 {
+    if body.fixed || body.mass <= 0.0 {
+        0.0
+    } else {
+        1.0 / body.mass
+    }
+}
+
+/// Returns `base + rate * h` element-wise.
+fn offset(base: &[Vector2<f32>], rate: &[Vector2<f32>], h: f32) -> Vec<Vector2<f32>> {
+    base.iter().zip(rate).map(|(b, r)| b + r * h).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn close_flyby_conserves_momentum() {
+        // One body swings past another at rest, close enough to deflect
+        // both noticeably.
+        let mut sim = Simulation::default();
+        sim.bodies.push(Body::new(
+            Vector2::zeros(),
+            Vector2::zeros(),
+            100.0,
+            5.0,
+            Body::DEFAULT_COLOR,
+        ));
+        sim.bodies.push(Body::new(
+            Vector2::new(-100.0, 20.0),
+            Vector2::new(5.0, 0.0),
+            100.0,
+            5.0,
+            Body::DEFAULT_COLOR,
+        ));
+        let momentum =
+            |sim: &Simulation| -> Vector2<f32> { sim.bodies.iter().map(|b| b.vel * b.mass).sum() };
+        let initial = momentum(&sim);
+        sim.compute_accelerations();
+        for _ in 0..4800 {
+            sim.step(1.0 / 120.0);
+        }
+        let first = &sim.bodies[0];
+        assert!((first.vel * first.mass).norm() > 0.1 * initial.norm());
+        assert!((momentum(&sim) - initial).norm() < 1e-3 * initial.norm());
+    }
+
+    #[test]
+    fn rk4_leaves_end_of_step_accelerations() {
+        let mut sim = Simulation {
+            integrator: Integrator::Rk4,
+            ..Default::default()
+        };
+        sim.bodies.push(Body::new(
+            Vector2::zeros(),
+            Vector2::zeros(),
+            100.0,
+            5.0,
+            Body::DEFAULT_COLOR,
+        ));
+        sim.bodies.push(Body::new(
+            Vector2::new(30.0, 0.0),
+            Vector2::new(0.0, 20.0),
+            1.0,
+            5.0,
+            Body::DEFAULT_COLOR,
+        ));
+        sim.compute_accelerations();
+        sim.step(0.1);
+        let positions: Vec<Vector2<f32>> = sim.bodies.iter().map(|b| b.pos).collect();
+        let fresh = sim.accelerations_at(&positions);
+        for (body, fresh) in sim.bodies.iter().zip(fresh) {
+            assert!(
+                (body.acc - fresh).norm() <= 1e-6 * fresh.norm(),
+                "{} vs {fresh}",
+                body.acc
+            );
+        }
+    }
+
+    #[test]
+    fn barnes_hut_matches_direct_sum() {
+        // Lopsided enough that, from the light body in the corner, the
+        // whole tree's center of mass looks far away even at the largest
+        // opening angle. That node contains the light body itself, so it
+        // must still be opened rather than approximated.
+        let mut sim = Simulation::default();
+        sim.bodies.push(Body::new(
+            Vector2::zeros(),
+            Vector2::zeros(),
+            1.0,
+            5.0,
+            Body::DEFAULT_COLOR,
+        ));
+        sim.bodies.push(Body::new(
+            Vector2::new(100.0, 100.0),
+            Vector2::zeros(),
+            3.0,
+            5.0,
+            Body::DEFAULT_COLOR,
+        ));
+        sim.bodies.push(Body::new(
+            Vector2::new(90.0, 95.0),
+            Vector2::zeros(),
+            0.5,
+            3.0,
+            Body::DEFAULT_COLOR,
+        ));
+        let positions: Vec<Vector2<f32>> = sim.bodies.iter().map(|b| b.pos).collect();
+        let direct = sim.accelerations_at(&positions);
+        sim.solver = GravitySolver::BarnesHut;
+        for theta in [0.5, 1.0] {
+            sim.theta = theta;
+            let approx = sim.accelerations_at(&positions);
+            for (d, a) in direct.iter().zip(&approx) {
+                assert!(
+                    (d - a).norm() <= 0.05 * d.norm(),
+                    "theta {theta}: {a} vs {d}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn elastic_head_on_bounce_swaps_velocities() {
+        let mut sim = Simulation {
+            collision_mode: CollisionMode::Bounce,
+            ..Default::default()
+        };
+        for (x, vx) in [(-4.9, 10.0), (4.9, -3.0)] {
+            let pos = Vector2::new(x, 0.0);
+            sim.bodies.push(Body::new(
+                pos,
+                Vector2::new(vx, 0.0),
+                1.0,
+                5.0,
+                Body::DEFAULT_COLOR,
+            ));
+        }
+        sim.compute_accelerations();
+        sim.step(1.0 / 120.0);
+        // Gravity between such light bodies barely changes the speeds.
+        assert!((sim.bodies[0].vel - Vector2::new(-3.0, 0.0)).norm() < 1e-3);
+        assert!((sim.bodies[1].vel - Vector2::new(10.0, 0.0)).norm() < 1e-3);
+    }
+
+    #[test]
+    fn fixed_body_stays_put() {
+        for integrator in Integrator::ALL {
+            let mut sim = Simulation {
+                integrator,
+                boundary: Boundary::Walls,
+                ..Default::default()
+            };
+            let mut pinned = Body::new(
+                Vector2::zeros(),
+                Vector2::new(5.0, 0.0),
+                1.0,
+                5.0,
+                Body::DEFAULT_COLOR,
+            );
+            pinned.fixed = true;
+            sim.bodies.push(pinned);
+            sim.bodies.push(Body::new(
+                Vector2::new(100.0, 0.0),
+                Vector2::zeros(),
+                1.0,
+                5.0,
+                Body::DEFAULT_COLOR,
+            ));
+            sim.compute_accelerations();
+            for _ in 0..120 {
+                sim.step(1.0 / 120.0);
+            }
+            assert_eq!(sim.bodies[0].pos, Vector2::zeros(), "{integrator:?}");
+        }
+    }
+
+    #[test]
+    fn gravity_force_matches_reference_formula() {
+        let (g, m1, m2) = (0.5, 3.0, 7.0);
+        let close = |a: Vector2<f32>, b: Vector2<f32>| (a - b).norm() <= 1e-5 * b.norm().max(1e-12);
+        // Unsoftened, the force is the textbook `normalize(d) G m1 m2 / r²`.
+        for to in [
+            Vector2::new(1.0, 0.0),
+            Vector2::new(-3.0, 4.0),
+            Vector2::new(250.0, -80.0),
+        ] {
+            let reference = to.normalize() * (g * m1 * m2 / to.norm_squared());
+            let force = gravity_force(g, Vector2::zeros(), to, m1, m2, 0.0);
+            assert!(close(force, reference), "{force} vs {reference}");
+        }
+        // Softened, including separations far below the softening length,
+        // it matches the previous `d G m1 m2 / s^(3/2)`, with s = r² + ε².
+        let softening = 2.0;
+        for to in [
+            Vector2::<f32>::new(10.0, 5.0),
+            Vector2::new(1e-3, 0.0),
+            Vector2::new(0.0, -1e-6),
+        ] {
+            let s = to.norm_squared() + softening * softening;
+            let reference = to * (g * m1 * m2 / (s * s.sqrt()));
+            let force = gravity_force(g, Vector2::zeros(), to, m1, m2, softening);
+            assert!(force.iter().all(|c| c.is_finite()));
+            assert!(close(force, reference), "{force} vs {reference}");
+        }
+        // Coincident and unsoftened, there is no direction to pull in.
+        let force = gravity_force(g, Vector2::zeros(), Vector2::zeros(), m1, m2, 0.0);
+        assert_eq!(force, Vector2::zeros());
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn simd_matches_scalar_exact_solver() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut sim = Simulation::default();
+        for _ in 0..37 {
+            let pos = Vector2::new(
+                rng.random_range(-500.0..500.0),
+                rng.random_range(-500.0..500.0),
+            );
+            let size = rng.random_range(1.0..10.0);
+            sim.bodies.push(Body::new(
+                pos,
+                Vector2::zeros(),
+                1.0,
+                size,
+                Body::DEFAULT_COLOR,
+            ));
+        }
+        let positions: Vec<Vector2<f32>> = sim.bodies.iter().map(|b| b.pos).collect();
+        let masses: Vec<f32> = sim.bodies.iter().map(|b| b.mass).collect();
+        let scalar = sim.exact_accelerations(&positions);
+        let simd = crate::simd::accelerations(&positions, &masses, sim.g, sim.softening);
+        for (a, b) in scalar.iter().zip(&simd) {
+            assert!((a - b).norm() <= 1e-5 * a.norm(), "{a} vs {b}");
+        }
+    }
+}